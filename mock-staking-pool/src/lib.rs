@@ -0,0 +1,72 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LookupMap;
+use near_sdk::json_types::{U128, ValidAccountId};
+use near_sdk::{env, near_bindgen, AccountId, PanicOnDefault, Promise};
+
+near_sdk::setup_alloc!();
+
+/*
+    Minimal staking pool for simulation tests, implements the part of the
+    core staking pool interface used by native grants. Unstaked balance can be
+    withdrawn right away and rewards are added with add_reward.
+*/
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct Contract {
+    staked: LookupMap<AccountId, u128>,
+    unstaked: LookupMap<AccountId, u128>,
+}
+
+#[near_bindgen]
+impl Contract {
+    #[init]
+    pub fn new() -> Self {
+        Self {
+            staked: LookupMap::new(b"s".to_vec()),
+            unstaked: LookupMap::new(b"u".to_vec()),
+        }
+    }
+
+    #[payable]
+    pub fn deposit_and_stake(&mut self) {
+        let account_id = env::predecessor_account_id();
+        let staked = self.staked.get(&account_id).unwrap_or(0);
+        self.staked.insert(&account_id, &(staked + env::attached_deposit()));
+    }
+
+    pub fn unstake(&mut self, amount: U128) {
+        let account_id = env::predecessor_account_id();
+        let staked = self.staked.get(&account_id).unwrap_or(0);
+        assert!(amount.0 <= staked, "ERR_NOT_ENOUGH_STAKED_BALANCE");
+        let unstaked = self.unstaked.get(&account_id).unwrap_or(0);
+        self.staked.insert(&account_id, &(staked - amount.0));
+        self.unstaked.insert(&account_id, &(unstaked + amount.0));
+    }
+
+    pub fn withdraw(&mut self, amount: U128) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let unstaked = self.unstaked.get(&account_id).unwrap_or(0);
+        assert!(amount.0 <= unstaked, "ERR_NOT_ENOUGH_UNSTAKED_BALANCE");
+        self.unstaked.insert(&account_id, &(unstaked - amount.0));
+        Promise::new(account_id).transfer(amount.0)
+    }
+
+    // attached deposit becomes staking rewards of account_id
+    #[payable]
+    pub fn add_reward(&mut self, account_id: ValidAccountId) {
+        let staked = self.staked.get(account_id.as_ref()).unwrap_or(0);
+        self.staked.insert(account_id.as_ref(), &(staked + env::attached_deposit()));
+    }
+
+    pub fn get_account_staked_balance(&self, account_id: ValidAccountId) -> U128 {
+        self.staked.get(account_id.as_ref()).unwrap_or(0).into()
+    }
+
+    pub fn get_account_unstaked_balance(&self, account_id: ValidAccountId) -> U128 {
+        self.unstaked.get(account_id.as_ref()).unwrap_or(0).into()
+    }
+
+    pub fn get_account_total_balance(&self, account_id: ValidAccountId) -> U128 {
+        (self.get_account_staked_balance(account_id.clone()).0 + self.get_account_unstaked_balance(account_id).0).into()
+    }
+}
@@ -0,0 +1,72 @@
+use near_sdk::json_types::{U128, ValidAccountId};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::utils::assert_one_yocto_or_contract_caller;
+use crate::Contract;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Acceleration {
+    // percentage (1-100) of the currently unvested amount
+    PercentOfUnvested(u8),
+    Full,
+}
+
+#[near_bindgen]
+impl Contract {
+    // e.g. a DAO that can trigger acceleration on a change of control
+    #[payable]
+    pub fn set_accelerator(&mut self, accelerator: Option<ValidAccountId>) {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+        self.assert_multisig_disabled();
+
+        self.internal_set_accelerator(accelerator.map(|accelerator| accelerator.into()));
+    }
+
+    pub fn accelerator(&self) -> Option<AccountId> {
+        self.accelerator.clone()
+    }
+
+    pub fn amount_accelerated(&self) -> U128 {
+        self.amount_accelerated.into()
+    }
+
+    #[payable]
+    pub fn accelerate(&mut self, acceleration: Acceleration) -> U128 {
+        let predecessor_account_id = env::predecessor_account_id();
+        assert!(
+            predecessor_account_id == self.owner() || Some(predecessor_account_id) == self.accelerator,
+            "ERR_NOT_OWNER_OR_ACCELERATOR"
+        );
+        assert_one_yocto_or_contract_caller();
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+
+        let amount_vested_before = self.internal_calculate_amount_vested();
+        assert!(self.amount > amount_vested_before, "ERR_NOTHING_LEFT_TO_ACCELERATE");
+
+        // the unvested amount is the part of the remainder its schedule hasn't reached yet,
+        // taking a share of the remainder takes the same share of the unvested amount
+        let amount_not_accelerated = self.amount - self.amount_accelerated;
+        let amount_to_accelerate = match acceleration {
+            Acceleration::PercentOfUnvested(percent) => {
+                assert!(percent > 0 && percent <= 100, "ERR_INVALID_PERCENT");
+                amount_not_accelerated * percent as u128 / 100
+            }
+            Acceleration::Full => amount_not_accelerated,
+        };
+
+        self.amount_accelerated = self.amount_accelerated.checked_add(amount_to_accelerate).expect("ERR_INTEGER_OVERFLOW");
+        self.internal_checkpoint();
+
+        // amount that vested right away
+        (self.internal_calculate_amount_vested() - amount_vested_before).into()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_set_accelerator(&mut self, accelerator: Option<AccountId>) {
+        self.accelerator = accelerator;
+    }
+}
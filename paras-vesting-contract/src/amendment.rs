@@ -0,0 +1,115 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen};
+
+use crate::utils::{assert_one_yocto_or_contract_caller, ONE_MONTH};
+use crate::{Contract, GrantType, VestingAsset, VestingCurve};
+
+// Schedule change proposed by the owner, only applied once the recipient accepts it.
+// Fields left as None keep their current value.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GrantAmendment {
+    pub duration: Option<U64>,
+    pub cliff_duration: Option<U64>,
+    pub top_up_amount: Option<U128>,
+    pub curve: Option<VestingCurve>,
+}
+
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn amend_grant(
+        &mut self,
+        duration: Option<U64>,
+        cliff_duration: Option<U64>,
+        top_up_amount: Option<U128>,
+        curve: Option<VestingCurve>,
+    ) {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+        self.assert_multisig_disabled();
+
+        self.internal_amend_grant(GrantAmendment {
+            duration,
+            cliff_duration,
+            top_up_amount,
+            curve,
+        });
+    }
+
+    #[payable]
+    pub fn accept_grant_amendment(&mut self) {
+        assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
+        assert_one_yocto();
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        let amendment = self.pending_amendment.take().expect("ERR_NO_PENDING_AMENDMENT");
+
+        let amount_vested_before = self.internal_calculate_amount_vested();
+
+        let duration = amendment.duration.map_or(self.duration, |duration| duration.0);
+        let cliff_duration = amendment.cliff_duration.map_or(self.cliff - self.start, |cliff_duration| cliff_duration.0);
+        assert!(cliff_duration < duration, "ERR_CLIFF_IS_HIGHER_THAN_DURATION");
+        assert!(duration > 0, "ERR_DURATION_IS_LESS_THAN_ZERO");
+
+        self.duration = duration;
+        self.cliff = self.start.checked_add(cliff_duration).expect("ERR_INTEGER_OVERFLOW");
+        if let Some(top_up_amount) = amendment.top_up_amount {
+            self.amount = self.amount.checked_add(top_up_amount.0).expect("ERR_INTEGER_OVERFLOW");
+        }
+        if let Some(curve) = amendment.curve {
+            self.curve = curve;
+        }
+        if self.curve == VestingCurve::Monthly {
+            assert!(self.duration >= ONE_MONTH, "ERR_DURATION_IS_LESS_THAN_ONE_MONTH");
+        }
+
+        // never take back what has already vested
+        assert!(
+            self.internal_calculate_amount_vested() >= amount_vested_before,
+            "ERR_AMENDMENT_REDUCES_VESTED_AMOUNT"
+        );
+        self.internal_checkpoint();
+    }
+
+    // either side can drop a pending amendment
+    #[payable]
+    pub fn cancel_grant_amendment(&mut self) {
+        let predecessor_account_id = env::predecessor_account_id();
+        assert!(
+            predecessor_account_id == self.owner() || predecessor_account_id == self.recipient(),
+            "ERR_NOT_OWNER_OR_RECIPIENT"
+        );
+        assert_one_yocto_or_contract_caller();
+        assert!(self.pending_amendment.take().is_some(), "ERR_NO_PENDING_AMENDMENT");
+    }
+
+    pub fn pending_amendment(&self) -> Option<GrantAmendment> {
+        self.pending_amendment.clone()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_amend_grant(&mut self, amendment: GrantAmendment) {
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        assert!(self.grant_type != GrantType::Milestone, "ERR_GRANT_HAS_NO_TIME_SCHEDULE");
+        // hybrid grant amount is the sum of its tranches
+        assert!(
+            self.grant_type == GrantType::Time || amendment.top_up_amount.is_none(),
+            "ERR_TOP_UP_NOT_SUPPORTED_FOR_MILESTONES"
+        );
+        // nothing would fund the top up of a native grant
+        assert!(
+            self.asset != VestingAsset::Native || amendment.top_up_amount.is_none(),
+            "ERR_TOP_UP_NOT_SUPPORTED_FOR_NATIVE_GRANT"
+        );
+        // extra tokens vest pro rata to the main token amount
+        assert!(
+            self.extra_tokens.is_empty() || amendment.top_up_amount.is_none(),
+            "ERR_TOP_UP_NOT_SUPPORTED_FOR_MULTI_TOKEN"
+        );
+
+        self.pending_amendment = Some(amendment);
+    }
+}
@@ -0,0 +1,77 @@
+use near_sdk::json_types::{U64, ValidAccountId};
+use near_sdk::{env, near_bindgen, AccountId, Promise};
+
+use crate::utils::{ext_fungible_token, GAS_FOR_FT_TRANSFER, NATIVE_STORAGE_RESERVE};
+use crate::{assert_valid_schedule, Contract, GrantTransferPolicy, GrantType, VestingAsset};
+
+#[near_bindgen]
+impl Contract {
+    // vests the attached deposit, storage is paid from the contract account's own balance
+    // so only the deposit is ever paid out
+    #[init]
+    #[payable]
+    pub fn new_native(
+        owner: ValidAccountId,
+        recipient: ValidAccountId,
+        start: U64,
+        duration: U64,
+        cliff_duration: U64,
+        revocable: bool,
+        transfer_policy: Option<GrantTransferPolicy>,
+    ) -> Self {
+        let amount = env::attached_deposit();
+        assert!(amount > 0, "ERR_DEPOSIT_IS_ZERO");
+        assert_valid_schedule(start.0, duration.0, cliff_duration.0);
+        let this = Self::internal_new(
+            owner.into(),
+            recipient.into(),
+            VestingAsset::Native,
+            amount,
+            start.0,
+            duration.0,
+            start.0.checked_add(cliff_duration.into()).expect("ERR_INTEGER_OVERFLOW"),
+            revocable,
+            transfer_policy.unwrap_or(GrantTransferPolicy::Allowed),
+            GrantType::Time,
+        );
+        // the account balance already includes the deposit
+        let storage_cost = env::storage_usage() as u128 * env::storage_byte_cost();
+        assert!(
+            env::account_balance().saturating_sub(amount) >= storage_cost + NATIVE_STORAGE_RESERVE,
+            "ERR_NOT_ENOUGH_BALANCE_FOR_STORAGE"
+        );
+        this
+    }
+
+    pub fn asset(&self) -> VestingAsset {
+        self.asset.clone()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_transfer_asset(&self, receiver_id: AccountId, amount: u128) -> Promise {
+        match &self.asset {
+            VestingAsset::FungibleToken(token) => ext_fungible_token::ft_transfer(
+                receiver_id,
+                amount.into(),
+                None,
+                token,
+                1,
+                GAS_FOR_FT_TRANSFER
+            ),
+            VestingAsset::Native => {
+                assert_storage_covered_after(amount);
+                Promise::new(receiver_id).transfer(amount)
+            }
+        }
+    }
+}
+
+// a payout must never dip into the balance that pays for the contract's storage
+fn assert_storage_covered_after(amount: u128) {
+    let storage_cost = env::storage_usage() as u128 * env::storage_byte_cost();
+    assert!(
+        env::account_balance().saturating_sub(amount) >= storage_cost,
+        "ERR_NOT_ENOUGH_BALANCE_FOR_STORAGE"
+    );
+}
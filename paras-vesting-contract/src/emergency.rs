@@ -0,0 +1,85 @@
+use near_sdk::json_types::{U64, ValidAccountId};
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::utils::{assert_one_yocto_or_contract_caller, EMERGENCY_PAUSE_COOLDOWN, MAX_EMERGENCY_PAUSE_DURATION};
+use crate::{Contract, Role};
+
+// Incident response: the guardian can stop claims, staking, revokes and recipient changes.
+// A pause always expires, and the next one needs the owner to re-arm it after the cooldown,
+// so a compromised guardian can't keep the grant frozen.
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn set_guardian(&mut self, guardian: Option<ValidAccountId>) {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+
+        self.guardian = guardian.map(|guardian| guardian.into());
+    }
+
+    pub fn guardian(&self) -> Option<AccountId> {
+        self.guardian.clone()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        env::block_timestamp() < self.paused_until
+    }
+
+    pub fn paused_until(&self) -> U64 {
+        self.paused_until.into()
+    }
+
+    pub fn is_pause_armed(&self) -> bool {
+        self.pause_armed
+    }
+
+    // allows one more pause, e.g. once the incident behind the last one is understood
+    #[payable]
+    pub fn arm_pause(&mut self) {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+
+        self.pause_armed = true;
+    }
+
+    #[payable]
+    pub fn pause(&mut self) {
+        assert_one_yocto_or_contract_caller();
+        let predecessor_account_id = env::predecessor_account_id();
+        assert!(
+            Some(predecessor_account_id.clone()) == self.guardian || self.internal_has_role(&predecessor_account_id, Role::Pauser),
+            "ERR_CALLER_NOT_GUARDIAN_OR_PAUSER"
+        );
+        assert!(!self.is_paused(), "ERR_ALREADY_PAUSED");
+        assert!(self.pause_armed, "ERR_PAUSE_NOT_ARMED");
+        assert!(
+            self.paused_until == 0
+                || env::block_timestamp() >= self.paused_until.checked_add(EMERGENCY_PAUSE_COOLDOWN).expect("ERR_INTEGER_OVERFLOW"),
+            "ERR_PAUSE_COOLDOWN"
+        );
+
+        self.pause_armed = false;
+        self.paused_until = env::block_timestamp().checked_add(MAX_EMERGENCY_PAUSE_DURATION).expect("ERR_INTEGER_OVERFLOW");
+    }
+
+    #[payable]
+    pub fn unpause(&mut self) {
+        assert_one_yocto_or_contract_caller();
+        let predecessor_account_id = env::predecessor_account_id();
+        assert!(
+            Some(predecessor_account_id.clone()) == self.guardian
+                || predecessor_account_id == self.owner()
+                || self.internal_has_role(&predecessor_account_id, Role::Pauser),
+            "ERR_NOT_GUARDIAN_OWNER_OR_PAUSER"
+        );
+        assert!(self.is_paused(), "ERR_NOT_PAUSED");
+
+        self.paused_until = env::block_timestamp();
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_not_paused(&self) {
+        assert!(!self.is_paused(), "ERR_CONTRACT_PAUSED");
+    }
+}
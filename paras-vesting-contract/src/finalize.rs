@@ -0,0 +1,107 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, AccountId, Promise, PromiseResult};
+
+use crate::nft::{internal_clear_nft_approvals, log_nft_burn};
+use crate::split::internal_pending_splits;
+use crate::utils::{
+    assert_one_yocto_or_contract_caller, ext_fungible_token, ext_self, ext_storage_management, GAS_FOR_FT_BALANCE_OF,
+    GAS_FOR_RESOLVE_FINALIZE, GAS_FOR_STORAGE_UNREGISTER
+};
+use crate::{Contract, VestingAsset};
+
+// Deletes the contract account once nothing is owed anymore, the remaining NEAR
+// (storage stake, keeper fund) goes to the owner. Token balances are checked first,
+// so tokens still held by the contract (e.g. a failed transfer refund) can't be lost,
+// then the token registrations are removed so their storage deposits come back.
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn finalize(&mut self) -> Promise {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+        self.assert_nothing_owed();
+
+        let tokens = self.internal_tokens();
+        if tokens.is_empty() {
+            return self.internal_finalize();
+        }
+
+        let mut balances = ext_fungible_token::ft_balance_of(env::current_account_id(), &tokens[0], 0, GAS_FOR_FT_BALANCE_OF);
+        for token in &tokens[1..] {
+            balances = balances.and(ext_fungible_token::ft_balance_of(env::current_account_id(), token, 0, GAS_FOR_FT_BALANCE_OF));
+        }
+        balances.then(ext_self::callback_finalize(
+            &env::current_account_id(),
+            0,
+            GAS_FOR_RESOLVE_FINALIZE
+        ))
+    }
+
+    #[private]
+    pub fn callback_finalize(&mut self) -> Promise {
+        for index in 0..env::promise_results_count() {
+            let balance = match env::promise_result(index) {
+                PromiseResult::Successful(value) => {
+                    near_sdk::serde_json::from_slice::<U128>(&value).expect("ERR_INVALID_BALANCE")
+                }
+                _ => env::panic(b"ERR_BALANCE_CHECK_FAILED"),
+            };
+            assert_eq!(balance.0, 0, "ERR_TOKENS_LEFT_IN_CONTRACT");
+        }
+        // a claim may have been rolled back in the meantime
+        self.assert_nothing_owed();
+
+        self.internal_finalize()
+    }
+}
+
+impl Contract {
+    // a revoke caps the amount at what was claimed, so revoked grants pass too
+    fn assert_nothing_owed(&self) {
+        assert!(self.amount_claimed >= self.amount, "ERR_GRANT_NOT_FULLY_CLAIMED");
+        assert_eq!(self.staked_principal, 0, "ERR_TOKENS_STAKED");
+        assert_eq!(internal_pending_splits(), 0, "ERR_SPLIT_PENDING");
+        assert!(
+            self.extra_tokens.iter().all(|extra_token| extra_token.amount_claimed.0 >= extra_token.amount.0),
+            "ERR_GRANT_NOT_FULLY_CLAIMED"
+        );
+    }
+
+    fn internal_tokens(&self) -> Vec<AccountId> {
+        let mut tokens: Vec<AccountId> = self.extra_tokens.iter().map(|extra_token| extra_token.token.clone()).collect();
+        if let VestingAsset::FungibleToken(token) = &self.asset {
+            tokens.insert(0, token.clone());
+        }
+        tokens
+    }
+
+    fn internal_finalize(&mut self) -> Promise {
+        internal_clear_nft_approvals(&self.recipient);
+        log_nft_burn(&self.recipient);
+        env::log(format!(
+            "EVENT_JSON:{}",
+            json!({
+                "standard": "paras-vesting",
+                "version": "1.0.0",
+                "event": "grant_finalized",
+                "data": [{
+                    "owner": self.owner,
+                    "recipient": self.recipient,
+                    "amount_claimed": U128(self.amount_claimed),
+                }],
+            })
+        ).as_bytes());
+
+        let tokens = self.internal_tokens();
+        if tokens.is_empty() {
+            return Promise::new(env::current_account_id()).delete_account(self.owner());
+        }
+        // balances are 0, so the registrations can go without force
+        let mut unregister = ext_storage_management::storage_unregister(Some(false), &tokens[0], 1, GAS_FOR_STORAGE_UNREGISTER);
+        for token in &tokens[1..] {
+            unregister = unregister.and(ext_storage_management::storage_unregister(Some(false), token, 1, GAS_FOR_STORAGE_UNREGISTER));
+        }
+        unregister.then(Promise::new(env::current_account_id()).delete_account(self.owner()))
+    }
+}
@@ -0,0 +1,66 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::ValidAccountId;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId};
+
+use crate::utils::assert_one_yocto_or_contract_caller;
+use crate::Contract;
+
+// set by the owner at init, decides whether the recipient can move the grant to another account
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum GrantTransferPolicy {
+    Allowed,
+    RequiresOwnerApproval,
+    Forbidden,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn transfer_policy(&self) -> GrantTransferPolicy {
+        self.transfer_policy.clone()
+    }
+
+    pub fn pending_grant_transfer(&self) -> Option<AccountId> {
+        self.pending_grant_transfer.clone()
+    }
+
+    // e.g. moving to a new wallet after a key compromise
+    #[payable]
+    pub fn transfer_grant(&mut self, new_recipient: ValidAccountId) {
+        assert_one_yocto();
+        assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        self.assert_not_paused();
+        assert_ne!(new_recipient.as_ref(), &self.recipient, "ERR_SAME_RECIPIENT");
+
+        match self.transfer_policy {
+            GrantTransferPolicy::Allowed => self.internal_set_recipient(new_recipient.into(), None),
+            GrantTransferPolicy::RequiresOwnerApproval => self.pending_grant_transfer = Some(new_recipient.into()),
+            GrantTransferPolicy::Forbidden => env::panic(b"ERR_GRANT_TRANSFER_FORBIDDEN"),
+        }
+    }
+
+    #[payable]
+    pub fn approve_grant_transfer(&mut self) {
+        assert_one_yocto_or_contract_caller();
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        self.assert_not_paused();
+        let new_recipient = self.pending_grant_transfer.clone().expect("ERR_NO_PENDING_GRANT_TRANSFER");
+
+        self.internal_set_recipient(new_recipient, None);
+    }
+
+    // owner rejects or recipient withdraws a pending transfer
+    #[payable]
+    pub fn cancel_grant_transfer(&mut self) {
+        assert_one_yocto_or_contract_caller();
+        let predecessor_account_id = env::predecessor_account_id();
+        assert!(
+            predecessor_account_id == self.owner() || predecessor_account_id == self.recipient(),
+            "ERR_NOT_OWNER_OR_RECIPIENT"
+        );
+        assert!(self.pending_grant_transfer.take().is_some(), "ERR_NO_PENDING_GRANT_TRANSFER");
+    }
+}
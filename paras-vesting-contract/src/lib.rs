@@ -1,19 +1,56 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::{env, near_bindgen};
+use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::{env, near_bindgen, BorshStorageKey};
 use near_sdk::json_types::{U128, U64, ValidAccountId};
-use near_sdk::{AccountId, Promise, PanicOnDefault, assert_one_yocto};
-
-use crate::utils::{ext_fungible_token, GAS_FOR_FT_TRANSFER, ONE_MONTH};
+use near_sdk::{AccountId, Promise, PromiseOrValue, PromiseResult, PanicOnDefault, assert_one_yocto};
+use near_contract_standards::storage_management::StorageBalance;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::amendment::GrantAmendment;
+pub use crate::acceleration::Acceleration;
+pub use crate::grant_transfer::GrantTransferPolicy;
+pub use crate::milestone::{Milestone, MilestoneArgs, MilestoneStatus};
+pub use crate::roles::{Role, RoleSet};
+pub use crate::split::{GrantShare, SplitGrant};
+pub use crate::multi_token::{TokenAllocation, TokenAllocationInfo, TokenAmount};
+pub use crate::multisig::{MultisigConfig, MultisigProposal, MultisigProposalKind};
+pub use crate::upgrade::StagedUpgrade;
+pub use crate::voting::{Checkpoint, VotingPower};
+use crate::migration::CURRENT_STATE_VERSION;
+use crate::nft::{internal_clear_nft_approvals, log_nft_mint, log_nft_transfer};
+use crate::utils::{
+    assert_one_yocto_or_contract_caller, ext_fungible_token, ext_self, ext_storage_management, is_promise_success, mul_div,
+    GAS_FOR_CLAIM_VESTED_TO, GAS_FOR_FT_TRANSFER_CALL, GAS_FOR_RESOLVE_CLAIM, GAS_FOR_RESOLVE_REVOKE, GAS_FOR_STORAGE_BALANCE_OF, ONE_MONTH
+};
+mod acceleration;
+mod amendment;
+mod emergency;
+mod finalize;
+mod asset;
+mod grant_transfer;
+mod migration;
+mod milestone;
+mod multi_token;
+mod multisig;
+mod nft;
+mod push;
+mod roles;
+mod split;
+mod staking;
+mod upgrade;
 mod utils;
+mod voting;
 
 near_sdk::setup_alloc!();
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
+    // must stay the first field, migrate reads it before parsing the rest
+    state_version: u8,
     owner: AccountId,
     recipient: AccountId,
-    token: AccountId,
+    asset: VestingAsset,
     amount: u128,
     amount_claimed: u128,
     start: u64, 
@@ -21,6 +58,114 @@ pub struct Contract {
     cliff: u64,
     revocable: bool,
     is_active: bool,
+    paused_intervals: Vec<PausedInterval>,
+    curve: VestingCurve,
+    pending_amendment: Option<GrantAmendment>,
+    accelerator: Option<AccountId>,
+    amount_accelerated: u128,
+    grant_type: GrantType,
+    approver: Option<AccountId>,
+    milestones: Vec<Milestone>,
+    push_min_interval: u64,
+    last_pushed_at: u64,
+    push_enabled: bool,
+    keeper_reward: u128,
+    keeper_fund: u128,
+    claim_operators: UnorderedSet<AccountId>,
+    transfer_policy: GrantTransferPolicy,
+    pending_grant_transfer: Option<AccountId>,
+    extra_tokens: Vec<TokenAllocation>,
+    staking_pools: UnorderedSet<AccountId>,
+    staking_pool: Option<AccountId>,
+    staked_principal: u128,
+    voting_delegate: Option<AccountId>,
+    unvested_voting_weight: u8,
+    checkpoints: Vector<Checkpoint>,
+    guardian: Option<AccountId>,
+    paused_until: u64,
+    // consumed by a pause, only the owner can arm the next one
+    pause_armed: bool,
+    roles: LookupMap<AccountId, RoleSet>,
+    multisig_signers: Vec<AccountId>,
+    multisig_threshold: u8,
+    multisig_proposals: UnorderedMap<u64, MultisigProposal>,
+    next_multisig_proposal_id: u64,
+}
+
+#[derive(BorshStorageKey, BorshSerialize)]
+pub(crate) enum StorageKey {
+    ClaimOperators,
+    StakingPools,
+    Checkpoints,
+    Roles,
+    MultisigProposals,
+    StagedUpgrade,
+    StagedUpgradeCode,
+    ApprovedUpgradeHash,
+    SplitCodeHash,
+    PendingSplits,
+    NftApprovals,
+    NftNextApprovalId,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum VestingAsset {
+    // NEP-141 token contract
+    FungibleToken(AccountId),
+    // NEAR, funded with the deposit attached to new_native
+    Native,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum GrantType {
+    // vests over time following start, cliff, duration and curve
+    Time,
+    // each tranche vests once the approver approves its milestone
+    Milestone,
+    // vests over time, but never more than the approved milestones
+    Hybrid,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VestingInfo {
+    pub owner: AccountId,
+    pub recipient: AccountId,
+    pub token: Option<AccountId>,
+    pub amount: U128,
+    pub amount_claimed: U128,
+    pub amount_vested: U128,
+    pub amount_vested_on_schedule: U128,
+    pub amount_accelerated: U128,
+    pub releasable_amount: U128,
+    pub start: U64,
+    pub cliff: U64,
+    pub duration: U64,
+    pub vesting_end: U64,
+    pub curve: VestingCurve,
+    pub grant_type: GrantType,
+    pub revocable: bool,
+    pub is_active: bool,
+    pub is_vesting_paused: bool,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum VestingCurve {
+    // vests in whole months
+    Monthly,
+    // vests continuously
+    Linear,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PausedInterval {
+    start: U64,
+    // None while the vesting is still paused
+    end: Option<U64>,
 }
 
 /* 
@@ -44,22 +189,85 @@ impl Contract {
         duration: U64,
         cliff_duration: U64,
         revocable: bool,
+        transfer_policy: Option<GrantTransferPolicy>,
+    ) -> Self {
+        assert_valid_schedule(start.0, duration.0, cliff_duration.0);
+        Self::internal_new(
+            owner.into(),
+            recipient.into(),
+            VestingAsset::FungibleToken(token.into()),
+            amount.into(),
+            start.0,
+            duration.0,
+            start.0.checked_add(cliff_duration.into()).expect("ERR_INTEGER_OVERFLOW"),
+            revocable,
+            transfer_policy.unwrap_or(GrantTransferPolicy::Allowed),
+            GrantType::Time,
+        )
+    }
+
+    fn internal_new(
+        owner: AccountId,
+        recipient: AccountId,
+        asset: VestingAsset,
+        amount: u128,
+        start: u64,
+        duration: u64,
+        cliff: u64,
+        revocable: bool,
+        transfer_policy: GrantTransferPolicy,
+        grant_type: GrantType,
     ) -> Self {
-        assert!(cliff_duration.0 < duration.0, "ERR_CLIFF_IS_HIGHER_THAN_DURATION");
-        assert!(duration.0 > 0, "ERR_DURATION_IS_LESS_THAN_ZERO");
-        assert!((start.0.checked_add(duration.into()).expect("ERR_INTEGER_OVERFLOW")) > env::block_timestamp().into(), "ERR_START_AND_DURATION_IS_IN_THE_PAST");
-        let this = Self {
-            owner: owner.into(),
-            recipient: recipient.into(),
-            token: token.into(),
-            amount: amount.into(),
+        let mut this = Self {
+            state_version: CURRENT_STATE_VERSION,
+            owner: owner,
+            recipient: recipient,
+            asset: asset,
+            amount: amount,
             amount_claimed: 0,
-            start: start.0,
-            duration: duration.0,
-            cliff: start.0.checked_add(cliff_duration.into()).expect("ERR_INTEGER_OVERFLOW"),
+            start: start,
+            duration: duration,
+            cliff: cliff,
             revocable: revocable,
             is_active: true,
+            paused_intervals: Vec::new(),
+            curve: VestingCurve::Monthly,
+            pending_amendment: None,
+            accelerator: None,
+            amount_accelerated: 0,
+            grant_type: grant_type,
+            approver: None,
+            milestones: Vec::new(),
+            push_min_interval: 0,
+            last_pushed_at: 0,
+            push_enabled: false,
+            keeper_reward: 0,
+            keeper_fund: 0,
+            claim_operators: UnorderedSet::new(StorageKey::ClaimOperators),
+            transfer_policy: transfer_policy,
+            pending_grant_transfer: None,
+            extra_tokens: Vec::new(),
+            staking_pools: UnorderedSet::new(StorageKey::StakingPools),
+            staking_pool: None,
+            staked_principal: 0,
+            voting_delegate: None,
+            unvested_voting_weight: 100,
+            checkpoints: Vector::new(StorageKey::Checkpoints),
+            guardian: None,
+            paused_until: 0,
+            pause_armed: true,
+            roles: LookupMap::new(StorageKey::Roles),
+            multisig_signers: vec![],
+            multisig_threshold: 0,
+            multisig_proposals: UnorderedMap::new(StorageKey::MultisigProposals),
+            next_multisig_proposal_id: 0,
         };
+        // the owner starts with every role and can hand them out
+        for role in [Role::Admin, Role::Revoker, Role::RecipientManager, Role::Pauser].iter() {
+            this.internal_grant_role(&this.owner.clone(), *role);
+        }
+        this.internal_checkpoint();
+        log_nft_mint(&this.recipient);
         this
     }
 
@@ -75,8 +283,12 @@ impl Contract {
         self.amount.into()
     }
 
-    pub fn token(&self) -> AccountId {
-        self.token.clone()
+    // None for native NEAR grants
+    pub fn token(&self) -> Option<AccountId> {
+        match &self.asset {
+            VestingAsset::FungibleToken(token) => Some(token.clone()),
+            VestingAsset::Native => None,
+        }
     }
 
     pub fn amount_claimed(&self) -> U128 {
@@ -99,24 +311,232 @@ impl Contract {
         self.revocable
     }
 
+    pub fn grant_type(&self) -> GrantType {
+        self.grant_type.clone()
+    }
+
+    pub fn curve(&self) -> VestingCurve {
+        self.curve.clone()
+    }
+
+    pub fn is_vesting_paused(&self) -> bool {
+        self.paused_intervals.last().map_or(false, |interval| interval.end.is_none())
+    }
+
+    pub fn paused_intervals(&self) -> Vec<PausedInterval> {
+        self.paused_intervals.clone()
+    }
+
+    pub fn paused_duration(&self) -> U64 {
+        self.internal_paused_duration(env::block_timestamp()).into()
+    }
+
+    // end of the vesting, pushed back by the time spent paused (assuming an ongoing pause ends now)
+    pub fn vesting_end(&self) -> U64 {
+        self.start
+            .checked_add(self.duration).expect("ERR_INTEGER_OVERFLOW")
+            .checked_add(self.internal_paused_duration(env::block_timestamp())).expect("ERR_INTEGER_OVERFLOW")
+            .into()
+    }
+
+    // amount is optional, the rest of the releasable amount stays in the contract
     #[payable]
-    pub fn claim_vested(&mut self) -> Promise {
+    pub fn claim_vested(&mut self, amount: Option<U128>) -> Promise {
+        assert_one_yocto();
+        // claim operators can claim on behalf of the recipient, tokens still go to the recipient
+        let predecessor_account_id = env::predecessor_account_id();
+        assert!(
+            predecessor_account_id == self.recipient() || self.claim_operators.contains(&predecessor_account_id),
+            "ERR_CALLER_NOT_RECIPIENT_OR_OPERATOR"
+        );
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        self.assert_not_paused();
+        let amount = self.internal_claimable_amount(amount);
+
+        self.internal_transfer_vested(self.recipient(), amount)
+    }
+
+    // send vested tokens to another account (e.g. a cold wallet or an exchange deposit account)
+    #[payable]
+    pub fn claim_vested_to(&mut self, receiver_id: ValidAccountId, amount: Option<U128>) -> Promise {
         assert_one_yocto();
         assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
         assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
-        let releasable = self.internal_releasable_amount();
-        assert!(releasable > 0, "ERR_NO_VESTED_AMOUNT_ARE_DUE");
+        self.assert_not_paused();
+        let amount = self.internal_claimable_amount(amount);
+
+        let token = match &self.asset {
+            VestingAsset::FungibleToken(token) => token.clone(),
+            // NEAR doesn't need a registration
+            VestingAsset::Native => return self.internal_transfer_vested(receiver_id.into(), amount),
+        };
+
+        // receiver must be registered on every token, otherwise ft_transfer fails
+        let mut registrations = ext_storage_management::storage_balance_of(
+            receiver_id.clone().into(),
+            &token,
+            0,
+            GAS_FOR_STORAGE_BALANCE_OF
+        );
+        for extra_token in self.extra_tokens.iter() {
+            registrations = registrations.and(ext_storage_management::storage_balance_of(
+                receiver_id.clone().into(),
+                &extra_token.token,
+                0,
+                GAS_FOR_STORAGE_BALANCE_OF
+            ));
+        }
+        registrations.then(ext_self::callback_claim_vested_to(
+            receiver_id.into(),
+            amount.into(),
+            &env::current_account_id(),
+            0,
+            GAS_FOR_CLAIM_VESTED_TO
+        ))
+    }
+
+    #[private]
+    pub fn callback_claim_vested_to(&mut self, receiver_id: AccountId, amount: U128) -> Promise {
+        // one result for the main token, then one per extra token
+        for index in 0..env::promise_results_count() {
+            let storage_balance = match env::promise_result(index) {
+                PromiseResult::Successful(value) => {
+                    near_sdk::serde_json::from_slice::<Option<StorageBalance>>(&value).unwrap_or(None)
+                }
+                _ => None,
+            };
+            assert!(storage_balance.is_some(), "ERR_RECEIVER_NOT_REGISTERED");
+        }
+
+        // state may have changed since claim_vested_to was called
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        assert!(amount.0 <= self.internal_releasable_amount(), "ERR_AMOUNT_EXCEEDS_RELEASABLE_AMOUNT");
+        assert!(amount.0 <= self.internal_liquid_balance(), "ERR_AMOUNT_EXCEEDS_LIQUID_BALANCE");
+
+        self.internal_transfer_vested(receiver_id, amount.0)
+    }
+
+    // roll back the claim if the transfer failed so the tokens are releasable again
+    #[private]
+    pub fn callback_claim_vested(&mut self, amount: U128) -> U128 {
+        if is_promise_success() {
+            amount
+        } else {
+            self.amount_claimed = self.amount_claimed.checked_sub(amount.0).expect("ERR_INTEGER_OVERFLOW");
+            self.internal_checkpoint();
+            U128(0)
+        }
+    }
+
+    // claim straight into another contract (e.g. a DEX, a staking farm or the Paras marketplace)
+    #[payable]
+    pub fn claim_vested_call(&mut self, receiver_id: ValidAccountId, amount: Option<U128>, msg: String) -> Promise {
+        assert_one_yocto();
+        assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        self.assert_not_paused();
+        let token = match &self.asset {
+            VestingAsset::FungibleToken(token) => token.clone(),
+            VestingAsset::Native => env::panic(b"ERR_NOT_SUPPORTED_FOR_NATIVE_GRANT"),
+        };
+        let amount = self.internal_claimable_amount(amount);
 
-        self.amount_claimed = self.amount_claimed.checked_add(releasable).expect("ERR_INTEGER_OVERFLOW");
+        self.amount_claimed = self.amount_claimed.checked_add(amount).expect("ERR_INTEGER_OVERFLOW");
+        self.internal_checkpoint();
 
-        ext_fungible_token::ft_transfer(
-            self.recipient.clone(),
-            releasable.into(),
+        ext_fungible_token::ft_transfer_call(
+            receiver_id.into(),
+            amount.into(),
             None,
-            &self.token,
+            msg,
+            &token,
             1,
-            GAS_FOR_FT_TRANSFER
-        )
+            GAS_FOR_FT_TRANSFER_CALL
+        ).then(ext_self::callback_claim_vested_call(
+            amount.into(),
+            &env::current_account_id(),
+            0,
+            GAS_FOR_RESOLVE_CLAIM
+        ))
+    }
+
+    // ft_transfer_call returns the amount used by the receiver after ft_resolve_transfer,
+    // whatever was refunded becomes releasable again
+    #[private]
+    pub fn callback_claim_vested_call(&mut self, amount: U128) -> U128 {
+        assert_eq!(env::promise_results_count(), 1, "ERR_TOO_MANY_RESULTS");
+        let amount_used = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                match near_sdk::serde_json::from_slice::<U128>(&value) {
+                    Ok(amount_used) => std::cmp::min(amount.0, amount_used.0),
+                    Err(_) => amount.0,
+                }
+            }
+            _ => 0,
+        };
+
+        let amount_refunded = amount.0 - amount_used;
+        if amount_refunded > 0 {
+            self.amount_claimed = self.amount_claimed.checked_sub(amount_refunded).expect("ERR_INTEGER_OVERFLOW");
+            self.internal_checkpoint();
+        }
+
+        amount_used.into()
+    }
+
+    fn internal_claimable_amount(&self, amount: Option<U128>) -> u128 {
+        let releasable = self.internal_releasable_amount();
+        assert!(releasable > 0, "ERR_NO_VESTED_AMOUNT_ARE_DUE");
+        // staked tokens can't be claimed until they are unstaked
+        let liquid_balance = self.internal_liquid_balance();
+        match amount {
+            Some(amount) => {
+                assert!(amount.0 > 0, "ERR_AMOUNT_IS_ZERO");
+                assert!(amount.0 <= releasable, "ERR_AMOUNT_EXCEEDS_RELEASABLE_AMOUNT");
+                assert!(amount.0 <= liquid_balance, "ERR_AMOUNT_EXCEEDS_LIQUID_BALANCE");
+                amount.0
+            }
+            None => {
+                assert!(liquid_balance > 0, "ERR_VESTED_AMOUNT_IS_STAKED");
+                std::cmp::min(releasable, liquid_balance)
+            }
+        }
+    }
+
+    fn internal_transfer_vested(&mut self, receiver_id: AccountId, amount: u128) -> Promise {
+        self.amount_claimed = self.amount_claimed.checked_add(amount).expect("ERR_INTEGER_OVERFLOW");
+        self.internal_transfer_extra_tokens(&receiver_id);
+        self.internal_checkpoint();
+
+        self.internal_transfer_asset(receiver_id, amount).then(ext_self::callback_claim_vested(
+            amount.into(),
+            &env::current_account_id(),
+            0,
+            GAS_FOR_RESOLVE_CLAIM
+        ))
+    }
+
+    pub fn get_vesting_info(&self) -> VestingInfo {
+        VestingInfo {
+            owner: self.owner(),
+            recipient: self.recipient(),
+            token: self.token(),
+            amount: self.amount(),
+            amount_claimed: self.amount_claimed(),
+            amount_vested: self.calculate_amount_vested(),
+            amount_vested_on_schedule: self.internal_calculate_amount_vested_on_schedule().into(),
+            amount_accelerated: self.amount_accelerated.into(),
+            releasable_amount: self.releasable_amount(),
+            start: self.start(),
+            cliff: self.cliff(),
+            duration: self.duration(),
+            vesting_end: self.vesting_end(),
+            curve: self.curve(),
+            grant_type: self.grant_type(),
+            revocable: self.revocable(),
+            is_active: self.is_active,
+            is_vesting_paused: self.is_vesting_paused(),
+        }
     }
 
     pub fn releasable_amount(&self) -> U128 {
@@ -131,66 +551,253 @@ impl Contract {
         self.internal_calculate_amount_vested().checked_sub(self.amount_claimed).expect("ERR_INTEGER_OVERFLOW")
     }
 
-    fn internal_calculate_amount_vested(&self) -> u128{
-        let block_timestamp = env::block_timestamp();
-        if block_timestamp < self.cliff {
-            return 0;
-        }
+    // total time spent paused between start and timestamp
+    fn internal_paused_duration(&self, timestamp: u64) -> u64 {
+        self.paused_intervals.iter().fold(0, |total, interval| {
+            let from = std::cmp::max(interval.start.0, self.start);
+            let to = std::cmp::min(interval.end.map_or(timestamp, |end| end.0), timestamp);
+            if to > from { total + (to - from) } else { total }
+        })
+    }
+
+    fn internal_calculate_amount_vested(&self) -> u128 {
+        amount_vested_with_acceleration(self.amount, self.amount_accelerated, self.internal_calculate_amount_vested_on_full_schedule())
+    }
 
-        let elapsed_time = block_timestamp - self.start;
+    fn internal_calculate_amount_vested_on_schedule(&self) -> u128 {
+        amount_vested_on_schedule(self.amount, self.amount_accelerated, self.internal_calculate_amount_vested_on_full_schedule())
+    }
 
-        if elapsed_time >= self.duration {
-            let vested_amount = self.amount;
-            return vested_amount;
-        } else {
-            let vested_amount = self.amount * ( elapsed_time / ONE_MONTH ) as u128 / ( self.duration / ONE_MONTH ) as u128;
-            return vested_amount;
+    fn internal_calculate_amount_vested_on_full_schedule(&self) -> u128 {
+        match self.grant_type {
+            GrantType::Time => self.internal_calculate_amount_vested_on_curve(),
+            GrantType::Milestone => self.internal_calculate_amount_approved(),
+            GrantType::Hybrid => std::cmp::min(
+                self.internal_calculate_amount_vested_on_curve(),
+                self.internal_calculate_amount_approved()
+            ),
         }
     }
 
+    fn internal_calculate_amount_vested_on_curve(&self) -> u128{
+        let block_timestamp = env::block_timestamp();
+        // paused time doesn't count, which also pushes back the cliff and the end date
+        let block_timestamp = block_timestamp - self.internal_paused_duration(block_timestamp);
+        amount_vested_on_curve(
+            self.internal_calculate_amount_on_curve(),
+            block_timestamp,
+            self.start,
+            self.cliff,
+            self.duration,
+            &self.curve
+        )
+    }
+
     #[payable]
-    pub fn revoke(&mut self) -> U128 {
-        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
-        assert_one_yocto();
+    pub fn revoke(&mut self) -> PromiseOrValue<U128> {
+        self.assert_role(Role::Revoker);
+        assert_one_yocto_or_contract_caller();
+        self.assert_multisig_disabled();
+
+        self.internal_start_revoke()
+    }
+
+    pub(crate) fn internal_start_revoke(&mut self) -> PromiseOrValue<U128> {
         assert!(self.revocable, "ERR_GRANT_NOT_REVOCABLE");
         assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        self.assert_not_paused();
+
+        // staked principal has to come back before the grant can be settled
+        if self.staked_principal > 0 {
+            // native stake only comes back after the unbonding period
+            assert!(self.asset != VestingAsset::Native, "ERR_NATIVE_STAKE_NOT_WITHDRAWN");
+            return self.internal_unstake(self.staked_principal).then(ext_self::callback_revoke(
+                self.staked_principal.into(),
+                &env::current_account_id(),
+                0,
+                GAS_FOR_RESOLVE_REVOKE
+            )).into();
+        }
+
+        PromiseOrValue::Value(self.internal_revoke())
+    }
+
+    #[private]
+    pub fn callback_revoke(&mut self, amount: U128) -> U128 {
+        assert!(is_promise_success(), "ERR_UNSTAKE_FAILED");
+        self.internal_on_unstaked(amount.0);
 
+        // the recipient may have staked again in the meantime, revoke has to be called again
+        if !self.is_active || self.staked_principal > 0 {
+            return U128(0);
+        }
+        self.internal_revoke()
+    }
+
+    fn internal_revoke(&mut self) -> U128 {
         let releasable: u128 = self.internal_releasable_amount();
         let amount_not_vested: u128 = self.amount.checked_sub(self.amount_claimed).expect("Integer underflow").checked_sub(releasable).expect("Integer underflow");
+        self.internal_revoke_extra_tokens();
 
+        // the releasable part is paid out below, the grant ends at what was vested
         self.is_active = false;
-        self.amount = 0;
+        self.amount_claimed += releasable;
+        self.amount = self.amount_claimed;
+        self.amount_accelerated = 0;
         self.start = 0;
         self.duration = 0;
         self.cliff = 0;
 
         // transfer current amount_vested to original recipient
-        ext_fungible_token::ft_transfer(
-            self.recipient(),
-            releasable.into(),
-            None,
-            &self.token,
-            1,
-            GAS_FOR_FT_TRANSFER
-        );
+        self.internal_transfer_asset(self.recipient(), releasable);
 
         // transfer leftover to recipient specified
-        ext_fungible_token::ft_transfer(
-            self.owner(),
-            amount_not_vested.into(),
-            None,
-            &self.token,
-            1,
-            GAS_FOR_FT_TRANSFER
-        );
+        self.internal_transfer_asset(self.owner(), amount_not_vested);
+        self.internal_checkpoint();
+
+        amount_not_vested.into()
+    }
+
+    #[payable]
+    pub fn pause_vesting(&mut self) {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+        self.assert_multisig_disabled();
+
+        self.internal_pause_vesting();
+    }
+
+    #[payable]
+    pub fn resume_vesting(&mut self) {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+        self.assert_multisig_disabled();
+
+        self.internal_resume_vesting();
+    }
+
+    pub(crate) fn internal_pause_vesting(&mut self) {
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        assert!(self.grant_type != GrantType::Milestone, "ERR_GRANT_HAS_NO_TIME_SCHEDULE");
+        assert!(!self.is_vesting_paused(), "ERR_VESTING_ALREADY_PAUSED");
+
+        self.paused_intervals.push(PausedInterval {
+            start: env::block_timestamp().into(),
+            end: None,
+        });
+        self.internal_checkpoint();
+    }
 
-        return amount_not_vested.into();
+    pub(crate) fn internal_resume_vesting(&mut self) {
+        assert!(self.is_vesting_paused(), "ERR_VESTING_NOT_PAUSED");
+
+        self.paused_intervals.last_mut().unwrap().end = Some(env::block_timestamp().into());
+        self.internal_checkpoint();
     }
 
     pub fn change_recipient(&mut self, recipient: AccountId) {
+        self.assert_role(Role::RecipientManager);
+        self.assert_multisig_disabled();
+        self.assert_not_paused();
+
+        self.internal_set_recipient(recipient, None);
+    }
+
+    // the new owner takes over the roles of the previous one
+    #[payable]
+    pub fn transfer_ownership(&mut self, owner: ValidAccountId) {
         assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+        self.assert_multisig_disabled();
+
+        self.internal_transfer_ownership(owner.into());
+    }
+
+    #[payable]
+    pub fn approve_claim_operator(&mut self, account_id: ValidAccountId) {
+        assert_one_yocto();
+        assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
+
+        assert!(self.claim_operators.insert(account_id.as_ref()), "ERR_ALREADY_CLAIM_OPERATOR");
+    }
+
+    #[payable]
+    pub fn revoke_claim_operator(&mut self, account_id: ValidAccountId) {
+        assert_one_yocto();
+        assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
+
+        assert!(self.claim_operators.remove(account_id.as_ref()), "ERR_NOT_CLAIM_OPERATOR");
+    }
+
+    pub fn claim_operators(&self) -> Vec<AccountId> {
+        self.claim_operators.to_vec()
+    }
+
+    // operators were approved by the previous recipient
+    fn internal_set_recipient(&mut self, recipient: AccountId, memo: Option<String>) {
+        log_nft_transfer(&self.recipient, &recipient, memo);
+        internal_clear_nft_approvals(&self.recipient);
+        self.recipient = recipient;
+        self.claim_operators.clear();
+        self.pending_grant_transfer = None;
+        self.push_enabled = false;
+        // delegation was chosen by the previous recipient too
+        self.voting_delegate = None;
+        self.internal_checkpoint();
+    }
+
+    fn internal_transfer_ownership(&mut self, owner: AccountId) {
+        let previous_owner = self.owner.clone();
+        if let Some(roles) = self.roles.get(&previous_owner) {
+            for role in roles.to_vec() {
+                self.internal_revoke_role(&previous_owner, role);
+                self.internal_grant_role(&owner, role);
+            }
+        }
+        self.owner = owner;
+    }
+}
+
+pub(crate) fn assert_valid_schedule(start: u64, duration: u64, cliff_duration: u64) {
+    assert!(cliff_duration < duration, "ERR_CLIFF_IS_HIGHER_THAN_DURATION");
+    assert!(duration > 0, "ERR_DURATION_IS_LESS_THAN_ZERO");
+    assert!((start.checked_add(duration).expect("ERR_INTEGER_OVERFLOW")) > env::block_timestamp(), "ERR_START_AND_DURATION_IS_IN_THE_PAST");
+}
+
+pub(crate) fn amount_vested_with_acceleration(amount: u128, amount_accelerated: u128, amount_vested_on_full_schedule: u128) -> u128 {
+    std::cmp::min(
+        amount,
+        amount_vested_on_schedule(amount, amount_accelerated, amount_vested_on_full_schedule).checked_add(amount_accelerated).expect("ERR_INTEGER_OVERFLOW")
+    )
+}
+
+// the part that wasn't accelerated keeps vesting on the original schedule,
+// so it still ends on the original end date
+pub(crate) fn amount_vested_on_schedule(amount: u128, amount_accelerated: u128, amount_vested_on_full_schedule: u128) -> u128 {
+    if amount == 0 {
+        return 0;
+    }
+    let amount_not_accelerated = amount.saturating_sub(amount_accelerated);
+    mul_div(amount_vested_on_full_schedule, amount_not_accelerated, amount)
+}
+
+// timestamp already has the paused time taken out
+pub(crate) fn amount_vested_on_curve(amount: u128, timestamp: u64, start: u64, cliff: u64, duration: u64, curve: &VestingCurve) -> u128 {
+    if timestamp < cliff {
+        return 0;
+    }
 
-        self.recipient = recipient.into();
+    let elapsed_time = timestamp - start;
+
+    if elapsed_time >= duration {
+        let vested_amount = amount;
+        return vested_amount;
+    } else {
+        let vested_amount = match curve {
+            VestingCurve::Monthly => amount * ( elapsed_time / ONE_MONTH ) as u128 / ( duration / ONE_MONTH ) as u128,
+            VestingCurve::Linear => mul_div(amount, elapsed_time as u128, duration as u128),
+        };
+        return vested_amount;
     }
 }
 
@@ -200,6 +807,7 @@ mod tests {
     use near_sdk::test_utils::{accounts, VMContextBuilder};
     use near_sdk::MockedBlockchain;
     use near_sdk::{testing_env};
+    use std::convert::TryInto;
 
     const _ONE_PARAS_TOKEN: U128 = U128(1 * 10u128.pow(18));
     const _TEN_PARAS_TOKEN: U128 = U128(10 * 10u128.pow(18));
@@ -223,10 +831,20 @@ mod tests {
         builder
     }
 
+    fn setup_milestone_contract() -> (VMContextBuilder, Contract) {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(0)).block_timestamp(JUNE_1_2021).build());
+        let contract = Contract::new_milestone_grant(accounts(1).into(), accounts(3).into(), accounts(2).into(), accounts(4).into(), vec![
+            MilestoneArgs { amount: U128::from(TOTAL_AMOUNT.0 / 2), expires_at: None },
+            MilestoneArgs { amount: U128::from(TOTAL_AMOUNT.0 / 2), expires_at: Some(U64::from(JUNE_1_2021 + SIX_MONTHS)) },
+        ], true, None);
+        (context, contract)
+    }
+
     fn setup_contract() -> (VMContextBuilder, Contract) {
         let mut context = VMContextBuilder::new();
         testing_env!(context.predecessor_account_id(accounts(0)).build());
-        let contract = Contract::new(accounts(1).into(), accounts(3).into(), accounts(2).into(), TOTAL_AMOUNT, U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true);
+        let contract = Contract::new(accounts(1).into(), accounts(3).into(), accounts(2).into(), TOTAL_AMOUNT, U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
         (context, contract)
     }
 
@@ -235,11 +853,11 @@ mod tests {
     fn test_new() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
-        let contract = Contract::new(accounts(1).into(), accounts(3).into(), accounts(2).into(), TOTAL_AMOUNT, U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), false);
+        let contract = Contract::new(accounts(1).into(), accounts(3).into(), accounts(2).into(), TOTAL_AMOUNT, U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), false, None);
         testing_env!(context.is_view(true).build());
         assert_eq!(contract.owner(), accounts(1).to_string());
         assert_eq!(contract.recipient(), accounts(3).to_string());
-        assert_eq!(contract.token(), accounts(2).to_string());
+        assert_eq!(contract.token(), Some(accounts(2).to_string()));
         assert_eq!(contract.amount(), TOTAL_AMOUNT);
         assert_eq!(contract.amount_claimed(), U128(0));
         assert_eq!(contract.start(), U64::from(JUNE_1_2021));
@@ -345,7 +963,7 @@ mod tests {
             .attached_deposit(1)
             .build()
         );
-        contract.claim_vested();
+        contract.claim_vested(None);
         assert_eq!(contract.amount_claimed, TOTAL_AMOUNT.0 * 7 / 24);
 
         // the next month
@@ -358,7 +976,7 @@ mod tests {
         assert_eq!(releasable_amount, TOTAL_AMOUNT.0 * 1 / 24);
 
         // claim
-        contract.claim_vested();
+        contract.claim_vested(None);
         assert_eq!(contract.amount_claimed, TOTAL_AMOUNT.0 * 8 / 24);
 
         // after vesting period over
@@ -374,7 +992,7 @@ mod tests {
         let releasable_amount = contract.internal_releasable_amount();
         assert_eq!(releasable_amount, u128::from(TOTAL_AMOUNT) - TOTAL_AMOUNT.0 * 8 / 24);
 
-        contract.claim_vested();
+        contract.claim_vested(None);
         assert_eq!(contract.amount_claimed, u128::from(TOTAL_AMOUNT));
 
         // after claim everything
@@ -400,7 +1018,7 @@ mod tests {
             .attached_deposit(1)
             .build()
         );
-        contract.claim_vested();
+        contract.claim_vested(None);
         assert_eq!(contract.amount_claimed, TOTAL_AMOUNT.0 * 7 / 24);
 
         testing_env!(context
@@ -413,53 +1031,2138 @@ mod tests {
         let current_amount_claimed = contract.amount_claimed();
         let releasable_amount = contract.internal_releasable_amount();
         // revoke
-        let amount_not_vested = contract.revoke();
+        let amount_not_vested = match contract.revoke() {
+            PromiseOrValue::Value(amount_not_vested) => amount_not_vested,
+            PromiseOrValue::Promise(_) => unreachable!(),
+        };
         assert_eq!(amount_not_vested, U128::from(u128::from(TOTAL_AMOUNT) - u128::from(current_amount_claimed) - u128::from(releasable_amount)));
 
         assert_eq!(contract.is_active, false);
         // assert_eq!(contract.recipient(), accounts(1).to_string());
-        assert_eq!(contract.amount, 0);
+        assert_eq!(contract.amount, current_amount_claimed.0 + releasable_amount);
+        assert_eq!(contract.amount_claimed, contract.amount);
         assert_eq!(contract.start, 0);
+        assert_eq!(contract.internal_releasable_amount(), 0);
+        // views keep working on a revoked grant with earlier claims
+        assert!(contract.nft_token("0".to_string()).is_some());
         assert_eq!(contract.duration, 0);
         assert_eq!(contract.cliff, 0);
 
     }
-    // NEGATIVE
     #[test]
-    #[should_panic(expected = "ERR_NO_VESTED_AMOUNT_ARE_DUE")]
-    fn test_invalid_claim_vested() {
+    fn test_pause_and_resume_vesting() {
         let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(contract.cliff + ONE_MONTH)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.pause_vesting();
+        assert!(contract.is_vesting_paused());
+
+        // nothing vests while paused
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(contract.cliff + ONE_MONTH * 3)
+            .attached_deposit(1)
+            .build()
+        );
+        let amount_vested: u128 = contract.calculate_amount_vested().into();
+        assert_eq!(amount_vested, TOTAL_AMOUNT.0 * 7 / 24);
+        assert_eq!(contract.paused_duration(), U64::from(ONE_MONTH * 2));
+
+        contract.resume_vesting();
+        assert!(!contract.is_vesting_paused());
+
         testing_env!(context
             .predecessor_account_id(accounts(3))
-            .block_timestamp(contract.cliff-1)
+            .block_timestamp(contract.cliff + ONE_MONTH * 4)
+            .build()
+        );
+        let amount_vested: u128 = contract.calculate_amount_vested().into();
+        assert_eq!(amount_vested, TOTAL_AMOUNT.0 * 8 / 24);
+        assert_eq!(contract.paused_duration(), U64::from(ONE_MONTH * 2));
+        assert_eq!(contract.vesting_end(), U64::from(JUNE_1_2021 + TWO_YEARS + ONE_MONTH * 2));
+
+        // end date is pushed back by the paused time
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS + ONE_MONTH)
+            .build()
+        );
+        let amount_vested: u128 = contract.calculate_amount_vested().into();
+        assert_eq!(amount_vested, TOTAL_AMOUNT.0 * 23 / 24);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS + ONE_MONTH * 2)
+            .build()
+        );
+        assert_eq!(contract.calculate_amount_vested(), TOTAL_AMOUNT);
+    }
+
+    #[test]
+    fn test_pause_vesting_before_cliff() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(contract.start + ONE_MONTH)
             .attached_deposit(1)
             .build()
         );
-        contract.claim_vested();
+        contract.pause_vesting();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(contract.start + ONE_MONTH + ONE_DAY * 10)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.resume_vesting();
+
+        // cliff is pushed back by ten days
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff + ONE_DAY)
+            .build()
+        );
+        assert_eq!(contract.calculate_amount_vested(), U128::from(0));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff + ONE_DAY * 10)
+            .build()
+        );
+        let amount_vested: u128 = contract.calculate_amount_vested().into();
+        assert_eq!(amount_vested, TOTAL_AMOUNT.0 * 6 / 24);
     }
+
     #[test]
-    #[should_panic(expected = "ERR_CALLER_NOT_RECIPIENT")]
-    fn test_invalid_claim_vested_caller_not_recipient() {
+    #[should_panic(expected = "ERR_NOT_OWNER")]
+    fn test_invalid_pause_vesting_caller_not_owner() {
         let (mut context, mut contract) = setup_contract();
         testing_env!(context
-            .predecessor_account_id(accounts(4))
-            .block_timestamp(contract.cliff+contract.duration)
+            .predecessor_account_id(accounts(3))
             .attached_deposit(1)
             .build()
         );
-        contract.claim_vested();
+        contract.pause_vesting();
     }
 
     #[test]
-    fn test_change_recipient() {
+    fn test_amend_grant() {
         let (mut context, mut contract) = setup_contract();
         testing_env!(context
             .predecessor_account_id(accounts(1))
+            .block_timestamp(contract.cliff + ONE_MONTH)
+            .attached_deposit(1)
             .build()
         );
+        // extend to three years, topping up so nothing vested is lost
+        contract.amend_grant(Some(U64::from(ONE_MONTH * 36)), None, Some(U128::from(TOTAL_AMOUNT.0 / 2)), None);
+        assert!(contract.pending_amendment().is_some());
+        assert_eq!(contract.duration(), U64::from(TWO_YEARS));
 
-        contract.change_recipient("changed.near".to_string());
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff + ONE_MONTH)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.accept_grant_amendment();
+        assert!(contract.pending_amendment().is_none());
+        assert_eq!(contract.duration(), U64::from(ONE_MONTH * 36));
+        assert_eq!(contract.cliff(), U64::from(JUNE_1_2021 + SIX_MONTHS));
+        assert_eq!(contract.amount(), U128::from(TOTAL_AMOUNT.0 * 3 / 2));
 
-        assert_eq!(contract.recipient(), "changed.near");
+        let amount_vested: u128 = contract.calculate_amount_vested().into();
+        assert_eq!(amount_vested, TOTAL_AMOUNT.0 * 3 / 2 * 7 / 36);
+    }
+
+    #[test]
+    fn test_amend_grant_linear_curve() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(contract.cliff + ONE_DAY * 15)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.amend_grant(None, None, None, Some(VestingCurve::Linear));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff + ONE_DAY * 15)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.accept_grant_amendment();
+        assert_eq!(contract.curve(), VestingCurve::Linear);
+
+        let amount_vested: u128 = contract.calculate_amount_vested().into();
+        assert_eq!(amount_vested, TOTAL_AMOUNT.0 / TWO_YEARS as u128 * (SIX_MONTHS + ONE_DAY * 15) as u128
+            + TOTAL_AMOUNT.0 % TWO_YEARS as u128 * (SIX_MONTHS + ONE_DAY * 15) as u128 / TWO_YEARS as u128);
+        assert!(amount_vested > TOTAL_AMOUNT.0 * 6 / 24);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_AMENDMENT_REDUCES_VESTED_AMOUNT")]
+    fn test_invalid_amend_grant_reduces_vested_amount() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(contract.cliff + ONE_MONTH)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.amend_grant(Some(U64::from(ONE_MONTH * 36)), None, None, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff + ONE_MONTH)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.accept_grant_amendment();
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_CALLER_NOT_RECIPIENT")]
+    fn test_invalid_accept_grant_amendment_caller_not_recipient() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.amend_grant(None, Some(U64::from(0)), None, None);
+        contract.accept_grant_amendment();
+    }
+
+    #[test]
+    fn test_accelerate() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(contract.cliff)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_accelerator(Some(accounts(4)));
+
+        // DAO accelerates half of the unvested amount
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .block_timestamp(contract.cliff)
+            .attached_deposit(1)
+            .build()
+        );
+        let amount_accelerated = contract.accelerate(Acceleration::PercentOfUnvested(50));
+        assert_eq!(amount_accelerated, U128::from(TOTAL_AMOUNT.0 * 18 / 24 / 2));
+
+        let vesting_info = contract.get_vesting_info();
+        assert_eq!(vesting_info.amount_vested_on_schedule, U128::from(TOTAL_AMOUNT.0 / 2 * 6 / 24));
+        assert_eq!(vesting_info.amount_accelerated, U128::from(TOTAL_AMOUNT.0 / 2));
+        assert_eq!(vesting_info.amount_vested, U128::from(TOTAL_AMOUNT.0 * 6 / 24 + TOTAL_AMOUNT.0 * 18 / 24 / 2));
+
+        // the remainder keeps its original end date
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff + ONE_MONTH * 12)
+            .build()
+        );
+        assert_eq!(contract.calculate_amount_vested(), U128::from(TOTAL_AMOUNT.0 / 2 + TOTAL_AMOUNT.0 / 2 * 18 / 24));
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.start + contract.duration - 1)
+            .build()
+        );
+        assert!(contract.calculate_amount_vested().0 < TOTAL_AMOUNT.0);
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.start + contract.duration)
+            .build()
+        );
+        assert_eq!(contract.calculate_amount_vested(), TOTAL_AMOUNT);
+
+        // full acceleration vests everything left
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(contract.cliff)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.accelerate(Acceleration::Full);
+        assert_eq!(contract.calculate_amount_vested(), TOTAL_AMOUNT);
+        assert_eq!(contract.releasable_amount(), TOTAL_AMOUNT);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_OWNER_OR_ACCELERATOR")]
+    fn test_invalid_accelerate_caller_not_accelerator() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.accelerate(Acceleration::Full);
+    }
+
+    #[test]
+    fn test_milestone_grant() {
+        let (mut context, mut contract) = setup_milestone_contract();
+        assert_eq!(contract.amount(), TOTAL_AMOUNT);
+        assert_eq!(contract.grant_type(), GrantType::Milestone);
+        assert_eq!(contract.calculate_amount_vested(), U128::from(0));
+
+        // approver unlocks the first tranche
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .block_timestamp(JUNE_1_2021 + ONE_MONTH)
+            .build()
+        );
+        contract.approve_milestone(0);
+        assert_eq!(contract.milestones()[0].status, MilestoneStatus::Approved);
+        assert_eq!(contract.releasable_amount(), U128::from(TOTAL_AMOUNT.0 / 2));
+
+        // claimed through the usual flow
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(JUNE_1_2021 + ONE_MONTH)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.claim_vested(None);
+        assert_eq!(contract.amount_claimed(), U128::from(TOTAL_AMOUNT.0 / 2));
+        assert_eq!(contract.releasable_amount(), U128::from(0));
+
+        // second tranche expires unapproved and goes back to the owner
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(JUNE_1_2021 + SIX_MONTHS)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.reclaim_expired_milestone(1);
+        assert_eq!(contract.milestones()[1].status, MilestoneStatus::Reclaimed);
+        assert_eq!(contract.amount(), U128::from(TOTAL_AMOUNT.0 / 2));
+        assert_eq!(contract.releasable_amount(), U128::from(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_MILESTONE_EXPIRED")]
+    fn test_invalid_approve_expired_milestone() {
+        let (mut context, mut contract) = setup_milestone_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .block_timestamp(JUNE_1_2021 + SIX_MONTHS)
+            .build()
+        );
+        contract.approve_milestone(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_MILESTONE_NOT_EXPIRED")]
+    fn test_invalid_reclaim_milestone_not_expired() {
+        let (mut context, mut contract) = setup_milestone_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(JUNE_1_2021 + ONE_MONTH)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.reclaim_expired_milestone(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_CALLER_NOT_APPROVER")]
+    fn test_invalid_approve_milestone_caller_not_approver() {
+        let (mut context, mut contract) = setup_milestone_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .build()
+        );
+        contract.approve_milestone(0);
+    }
+
+    #[test]
+    fn test_hybrid_grant() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let milestones = (0..4).map(|_| MilestoneArgs { amount: U128::from(TOTAL_AMOUNT.0 / 4), expires_at: None }).collect();
+        let mut contract = Contract::new_hybrid_grant(accounts(1).into(), accounts(3).into(), accounts(2).into(), U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), accounts(4).into(), milestones, true, None);
+        assert_eq!(contract.amount(), TOTAL_AMOUNT);
+        assert_eq!(contract.grant_type(), GrantType::Hybrid);
+
+        // approved but not yet vested in time
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .block_timestamp(contract.cliff - 1)
+            .build()
+        );
+        contract.approve_milestone(0);
+        assert_eq!(contract.calculate_amount_vested(), U128::from(0));
+
+        // vested in time but capped by the approved milestones
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .block_timestamp(contract.cliff + SIX_MONTHS)
+            .build()
+        );
+        assert_eq!(contract.calculate_amount_vested(), U128::from(TOTAL_AMOUNT.0 / 4));
+
+        contract.approve_milestone(1);
+        contract.approve_milestone(2);
+        assert_eq!(contract.calculate_amount_vested(), U128::from(TOTAL_AMOUNT.0 * 12 / 24));
+    }
+
+    #[test]
+    fn test_hybrid_reclaim_expired_milestone() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut milestones: Vec<MilestoneArgs> = (0..4).map(|_| MilestoneArgs { amount: U128::from(TOTAL_AMOUNT.0 / 4), expires_at: None }).collect();
+        milestones[3].expires_at = Some(U64::from(JUNE_1_2021 + SIX_MONTHS * 2));
+        let mut contract = Contract::new_hybrid_grant(accounts(1).into(), accounts(3).into(), accounts(2).into(), U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), accounts(4).into(), milestones, true, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .block_timestamp(JUNE_1_2021 + SIX_MONTHS)
+            .build()
+        );
+        contract.approve_milestone(0);
+        contract.approve_milestone(1);
+        contract.approve_milestone(2);
+
+        // half the time has passed, three tranches are approved
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(JUNE_1_2021 + SIX_MONTHS * 2)
+            .attached_deposit(1)
+            .build()
+        );
+        assert_eq!(contract.calculate_amount_vested(), U128::from(TOTAL_AMOUNT.0 / 2));
+        contract.reclaim_expired_milestone(3);
+        assert_eq!(contract.amount(), U128::from(TOTAL_AMOUNT.0 / 4 * 3));
+        assert_eq!(contract.calculate_amount_vested(), U128::from(TOTAL_AMOUNT.0 / 2));
+
+        // the time curve still ends on the original end date, capped by the approved tranches
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS)
+            .build()
+        );
+        assert_eq!(contract.calculate_amount_vested(), U128::from(TOTAL_AMOUNT.0 / 4 * 3));
+    }
+
+    // NEGATIVE
+    #[test]
+    #[should_panic(expected = "ERR_NO_VESTED_AMOUNT_ARE_DUE")]
+    fn test_invalid_claim_vested() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff-1)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.claim_vested(None);
+    }
+    #[test]
+    #[should_panic(expected = "ERR_CALLER_NOT_RECIPIENT")]
+    fn test_invalid_claim_vested_caller_not_recipient() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .block_timestamp(contract.cliff+contract.duration)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.claim_vested(None);
+    }
+
+    #[test]
+    fn test_claim_vested_partial() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff + ONE_MONTH)
+            .attached_deposit(1)
+            .build()
+        );
+        // 7/24 is vested, claim a third of it
+        let vested: u128 = TOTAL_AMOUNT.0 * 7 / 24;
+        contract.claim_vested(Some(U128::from(vested / 3)));
+        assert_eq!(contract.amount_claimed, vested / 3);
+        assert_eq!(contract.internal_releasable_amount(), vested - vested / 3);
+
+        contract.claim_vested(Some(U128::from(vested / 3)));
+        assert_eq!(contract.amount_claimed, vested / 3 * 2);
+
+        // after vesting is over the last claim picks up the rounding leftovers
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff + contract.duration)
+            .attached_deposit(1)
+            .build()
+        );
+        let releasable_amount = contract.internal_releasable_amount();
+        assert_eq!(releasable_amount, TOTAL_AMOUNT.0 - vested / 3 * 2);
+
+        contract.claim_vested(Some(U128::from(releasable_amount)));
+        assert_eq!(contract.amount_claimed, TOTAL_AMOUNT.0);
+        assert_eq!(contract.internal_releasable_amount(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_AMOUNT_EXCEEDS_RELEASABLE_AMOUNT")]
+    fn test_invalid_claim_vested_partial_amount_exceeds_releasable() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff + ONE_MONTH)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.claim_vested(Some(U128::from(TOTAL_AMOUNT.0 * 7 / 24 + 1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_AMOUNT_IS_ZERO")]
+    fn test_invalid_claim_vested_partial_amount_zero() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff + ONE_MONTH)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.claim_vested(Some(U128::from(0)));
+    }
+
+    #[test]
+    fn test_claim_vested_call() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff + ONE_MONTH)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.claim_vested_call(accounts(4), None, "".to_string());
+        // counted as claimed until the transfer is resolved
+        assert_eq!(contract.amount_claimed, TOTAL_AMOUNT.0 * 7 / 24);
+        assert_eq!(contract.internal_releasable_amount(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_CALLER_NOT_RECIPIENT")]
+    fn test_invalid_claim_vested_call_caller_not_recipient() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .block_timestamp(contract.cliff + ONE_MONTH)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.claim_vested_call(accounts(4), None, "".to_string());
+    }
+
+    #[test]
+    fn test_push_vested() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_push_config(U64::from(ONE_MONTH), U128::from(10u128.pow(22)));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_push_enabled(true);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(10u128.pow(24))
+            .build()
+        );
+        contract.deposit_keeper_fund();
+
+        // any keeper can push to the recipient
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .block_timestamp(contract.cliff)
+            .attached_deposit(0)
+            .build()
+        );
+        contract.push_vested();
+        assert_eq!(contract.amount_claimed, TOTAL_AMOUNT.0 * 6 / 24);
+        assert_eq!(contract.last_pushed_at(), U64::from(contract.cliff));
+        // keeper reward is reserved until the transfer is resolved
+        assert_eq!(contract.keeper_fund(), U128::from(10u128.pow(24) - 10u128.pow(22)));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .block_timestamp(contract.cliff + ONE_MONTH)
+            .build()
+        );
+        contract.push_vested();
+        assert_eq!(contract.amount_claimed, TOTAL_AMOUNT.0 * 7 / 24);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PUSH_TOO_EARLY")]
+    fn test_invalid_push_vested_too_early() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_push_config(U64::from(ONE_MONTH), U128::from(0));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_push_enabled(true);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .block_timestamp(contract.cliff)
+            .attached_deposit(0)
+            .build()
+        );
+        contract.push_vested();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .block_timestamp(contract.cliff + ONE_MONTH - 1)
+            .build()
+        );
+        contract.push_vested();
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PUSH_NOT_ENABLED")]
+    fn test_invalid_push_vested_not_enabled() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .block_timestamp(contract.cliff)
+            .build()
+        );
+        contract.push_vested();
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_AMOUNT_EXCEEDS_RELEASABLE_AMOUNT")]
+    fn test_invalid_claim_vested_to_amount_exceeds_releasable() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.claim_vested_to(accounts(4), Some(U128::from(TOTAL_AMOUNT.0 * 6 / 24 + 1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_CALLER_NOT_RECIPIENT")]
+    fn test_invalid_claim_vested_to_caller_not_recipient() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .block_timestamp(contract.cliff)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.claim_vested_to(accounts(4), None);
+    }
+
+    #[test]
+    fn test_claim_operator() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.approve_claim_operator(accounts(4));
+        assert_eq!(contract.claim_operators(), vec![accounts(4).to_string()]);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .block_timestamp(contract.cliff)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.claim_vested(None);
+        assert_eq!(contract.amount_claimed, TOTAL_AMOUNT.0 * 6 / 24);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.revoke_claim_operator(accounts(4));
+        assert!(contract.claim_operators().is_empty());
+    }
+
+    #[test]
+    fn test_change_recipient_clears_claim_operators() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.approve_claim_operator(accounts(4));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .build()
+        );
+        contract.change_recipient("changed.near".to_string());
+        assert!(contract.claim_operators().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_CALLER_NOT_RECIPIENT")]
+    fn test_invalid_approve_claim_operator_caller_not_recipient() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.approve_claim_operator(accounts(4));
+    }
+
+    #[test]
+    fn test_multi_token_claim_vested() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_multi_token(accounts(1).into(), accounts(3).into(), vec![
+            TokenAmount { token: accounts(2), amount: TOTAL_AMOUNT },
+            TokenAmount { token: accounts(5), amount: U128(1000) },
+        ], U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
+        assert_eq!(contract.extra_tokens().len(), 1);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + SIX_MONTHS + 10)
+            .build()
+        );
+        let token_allocations = contract.token_allocations();
+        assert_eq!(token_allocations[0].releasable_amount, U128(TOTAL_AMOUNT.0 / 4));
+        assert_eq!(token_allocations[1].token, Some(accounts(5).to_string()));
+        assert_eq!(token_allocations[1].releasable_amount, U128(250));
+
+        // extra tokens are released in proportion to the claimed amount
+        contract.claim_vested(Some(U128(TOTAL_AMOUNT.0 / 8)));
+        assert_eq!(contract.amount_claimed(), U128(TOTAL_AMOUNT.0 / 8));
+        assert_eq!(contract.extra_tokens()[0].amount_claimed, U128(125));
+        assert_eq!(contract.token_allocations()[1].releasable_amount, U128(125));
+
+        contract.claim_vested(None);
+        assert_eq!(contract.extra_tokens()[0].amount_claimed, U128(250));
+        assert_eq!(contract.token_allocations()[1].releasable_amount, U128(0));
+    }
+
+    #[test]
+    fn test_claim_extra_tokens() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_multi_token(accounts(1).into(), accounts(3).into(), vec![
+            TokenAmount { token: accounts(2), amount: TOTAL_AMOUNT },
+            TokenAmount { token: accounts(5), amount: U128(1000) },
+        ], U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS)
+            .build()
+        );
+        contract.claim_vested(None);
+        assert_eq!(contract.extra_tokens()[0].amount_claimed, U128(1000));
+
+        // the extra token transfer failed, the main token has nothing left
+        testing_env!(context.predecessor_account_id(accounts(0)).build(), Default::default(), Default::default(), Default::default(), vec![PromiseResult::Failed]);
+        assert_eq!(contract.callback_claim_extra_token(0, U128(1000)), U128(0));
+        assert_eq!(contract.extra_tokens()[0].amount_claimed, U128(0));
+        assert_eq!(contract.releasable_amount(), U128(0));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS)
+            .build()
+        );
+        contract.claim_extra_tokens();
+        assert_eq!(contract.extra_tokens()[0].amount_claimed, U128(1000));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NO_VESTED_AMOUNT_ARE_DUE")]
+    fn test_invalid_claim_extra_tokens_nothing_releasable() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_multi_token(accounts(1).into(), accounts(3).into(), vec![
+            TokenAmount { token: accounts(2), amount: TOTAL_AMOUNT },
+            TokenAmount { token: accounts(5), amount: U128(1000) },
+        ], U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + SIX_MONTHS + 10)
+            .build()
+        );
+        contract.claim_vested(None);
+        contract.claim_extra_tokens();
+    }
+
+    #[test]
+    fn test_multi_token_revoke() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_multi_token(accounts(1).into(), accounts(3).into(), vec![
+            TokenAmount { token: accounts(2), amount: TOTAL_AMOUNT },
+            TokenAmount { token: accounts(5), amount: U128(1000) },
+        ], U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
+
+        testing_env!(context
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + SIX_MONTHS + 10)
+            .build()
+        );
+        contract.revoke();
+        let extra_token = &contract.extra_tokens()[0];
+        assert_eq!(extra_token.amount_claimed, U128(250));
+        assert_eq!(extra_token.amount, U128(250));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DUPLICATE_TOKEN")]
+    fn test_invalid_multi_token_duplicate_token() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        Contract::new_multi_token(accounts(1).into(), accounts(3).into(), vec![
+            TokenAmount { token: accounts(2), amount: TOTAL_AMOUNT },
+            TokenAmount { token: accounts(2), amount: U128(1000) },
+        ], U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
+    }
+
+    #[test]
+    fn test_stake_unvested() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.add_staking_pool(accounts(5));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021)
+            .build()
+        );
+        contract.stake_unvested(accounts(5), U128(TOTAL_AMOUNT.0 / 4 * 3));
+        assert_eq!(contract.staking_pool(), Some(accounts(5).to_string()));
+        assert_eq!(contract.staked_principal(), U128(TOTAL_AMOUNT.0 / 4 * 3));
+        assert_eq!(contract.liquid_balance(), U128(TOTAL_AMOUNT.0 / 4));
+
+        // only the liquid part of the vested amount can be claimed
+        testing_env!(context
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS)
+            .build()
+        );
+        assert_eq!(contract.releasable_amount(), TOTAL_AMOUNT);
+        contract.claim_vested(None);
+        assert_eq!(contract.amount_claimed(), U128(TOTAL_AMOUNT.0 / 4));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_AMOUNT_EXCEEDS_UNVESTED_AMOUNT")]
+    fn test_invalid_stake_vested_amount() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.add_staking_pool(accounts(5));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS / 2)
+            .build()
+        );
+        contract.stake_unvested(accounts(5), U128(TOTAL_AMOUNT.0 / 4 * 3));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_STAKING_POOL_NOT_WHITELISTED")]
+    fn test_invalid_stake_not_whitelisted() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021)
+            .build()
+        );
+        contract.stake_unvested(accounts(5), U128(1));
+    }
+
+    #[test]
+    fn test_deposit_and_stake() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(TOTAL_AMOUNT.0).build());
+        let mut contract = Contract::new_native(accounts(1).into(), accounts(3).into(), U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
+        testing_env!(context.attached_deposit(1).build());
+        contract.add_staking_pool(accounts(5));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.deposit_and_stake(accounts(5), U128(TOTAL_AMOUNT.0 / 2));
+        assert_eq!(contract.staked_principal(), U128(TOTAL_AMOUNT.0 / 2));
+        assert_eq!(contract.liquid_balance(), U128(TOTAL_AMOUNT.0 / 2));
+
+        // unstaked NEAR stays at the pool until it is withdrawn
+        contract.unstake(accounts(5), U128(TOTAL_AMOUNT.0 / 2));
+        assert_eq!(contract.staked_principal(), U128(TOTAL_AMOUNT.0 / 2));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NATIVE_STAKE_NOT_WITHDRAWN")]
+    fn test_invalid_revoke_native_staked() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(TOTAL_AMOUNT.0).build());
+        let mut contract = Contract::new_native(accounts(1).into(), accounts(3).into(), U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
+        testing_env!(context.attached_deposit(1).build());
+        contract.add_staking_pool(accounts(5));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .build()
+        );
+        contract.deposit_and_stake(accounts(5), U128(TOTAL_AMOUNT.0 / 2));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .build()
+        );
+        contract.revoke();
+    }
+
+    #[test]
+    fn test_voting_power() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS / 2)
+            .build()
+        );
+        let voting_power = contract.voting_power(accounts(3), None);
+        assert_eq!(voting_power.unvested, U128(TOTAL_AMOUNT.0 / 2));
+        assert_eq!(voting_power.vested_unclaimed, U128(TOTAL_AMOUNT.0 / 2));
+        assert_eq!(voting_power.weighted_total, TOTAL_AMOUNT);
+
+        contract.delegate_voting_power(Some(accounts(4)));
+        assert_eq!(contract.voting_power(accounts(3), None).weighted_total, U128(0));
+        assert_eq!(contract.voting_power(accounts(4), None).weighted_total, TOTAL_AMOUNT);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS)
+            .build()
+        );
+        contract.set_unvested_voting_weight(0);
+        assert_eq!(contract.voting_power(accounts(4), None).weighted_total, TOTAL_AMOUNT);
+
+        // history is read from the checkpoints, with the weight of the time
+        let voting_power = contract.voting_power(accounts(4), Some(U64::from(JUNE_1_2021 + TWO_YEARS / 2)));
+        assert_eq!(voting_power.unvested, U128(TOTAL_AMOUNT.0 / 2));
+        assert_eq!(voting_power.weighted_total, TOTAL_AMOUNT);
+        assert_eq!(contract.voting_power(accounts(3), Some(U64::from(JUNE_1_2021 + SIX_MONTHS - 1))).unvested, TOTAL_AMOUNT);
+        // vesting keeps going after the last checkpoint before at_timestamp
+        let voting_power = contract.voting_power(accounts(4), Some(U64::from(JUNE_1_2021 + TWO_YEARS * 3 / 4)));
+        assert_eq!(voting_power.unvested, U128(TOTAL_AMOUNT.0 / 4));
+        assert_eq!(voting_power.vested_unclaimed, U128(TOTAL_AMOUNT.0 * 3 / 4));
+        assert_eq!(contract.checkpoints(None, None).len(), 3);
+    }
+
+    #[test]
+    fn test_voting_power_vesting_paused() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + ONE_MONTH * 12)
+            .build()
+        );
+        contract.pause_vesting();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + ONE_MONTH * 18)
+            .build()
+        );
+        contract.resume_vesting();
+        assert_eq!(contract.checkpoints(None, None).len(), 3);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS)
+            .build()
+        );
+        // nothing vests while paused
+        let voting_power = contract.voting_power(accounts(3), Some(U64::from(JUNE_1_2021 + ONE_MONTH * 15)));
+        assert_eq!(voting_power.vested_unclaimed, U128(TOTAL_AMOUNT.0 / 2));
+        let voting_power = contract.voting_power(accounts(3), Some(U64::from(JUNE_1_2021 + ONE_MONTH * 21)));
+        assert_eq!(voting_power.vested_unclaimed, U128(TOTAL_AMOUNT.0 * 15 / 24));
+        assert_eq!(voting_power.unvested, U128(TOTAL_AMOUNT.0 - TOTAL_AMOUNT.0 * 15 / 24));
+    }
+
+    #[test]
+    fn test_emergency_pause_expires() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_guardian(Some(accounts(5)));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .block_timestamp(JUNE_1_2021 + SIX_MONTHS)
+            .build()
+        );
+        contract.pause();
+        assert!(contract.is_paused());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(JUNE_1_2021 + SIX_MONTHS + 7 * 86400000000000)
+            .build()
+        );
+        assert!(!contract.is_paused());
+        contract.claim_vested(None);
+        assert_eq!(contract.amount_claimed(), U128(TOTAL_AMOUNT.0 / 4));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_CONTRACT_PAUSED")]
+    fn test_invalid_claim_vested_paused() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_guardian(Some(accounts(5)));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .block_timestamp(JUNE_1_2021 + SIX_MONTHS)
+            .build()
+        );
+        contract.pause();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .build()
+        );
+        contract.claim_vested(None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PAUSE_COOLDOWN")]
+    fn test_invalid_pause_during_cooldown() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_guardian(Some(accounts(5)));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .block_timestamp(JUNE_1_2021)
+            .build()
+        );
+        contract.pause();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + 7 * 86400000000000)
+            .build()
+        );
+        contract.arm_pause();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .build()
+        );
+        contract.pause();
+    }
+
+    #[test]
+    fn test_arm_pause() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_guardian(Some(accounts(5)));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .block_timestamp(JUNE_1_2021)
+            .build()
+        );
+        contract.pause();
+        assert!(!contract.is_pause_armed());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(JUNE_1_2021 + 8 * 86400000000000)
+            .build()
+        );
+        contract.arm_pause();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .build()
+        );
+        contract.pause();
+        assert!(contract.is_paused());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PAUSE_NOT_ARMED")]
+    fn test_invalid_pause_not_armed() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_guardian(Some(accounts(5)));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .block_timestamp(JUNE_1_2021)
+            .build()
+        );
+        contract.pause();
+
+        // long after the cooldown, but the owner didn't arm it again
+        testing_env!(context
+            .block_timestamp(JUNE_1_2021 + 30 * 86400000000000)
+            .build()
+        );
+        contract.pause();
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_CONTRACT_PAUSED")]
+    fn test_invalid_stake_unvested_paused() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.add_staking_pool(accounts(5));
+        contract.pause();
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .build()
+        );
+        contract.stake_unvested(accounts(5), U128(1));
+    }
+
+    #[test]
+    fn test_new_native() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(TOTAL_AMOUNT.0).build());
+        let contract = Contract::new_native(accounts(1).into(), accounts(3).into(), U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
+        assert_eq!(contract.asset(), VestingAsset::Native);
+        assert_eq!(contract.token(), None);
+        assert_eq!(contract.amount(), TOTAL_AMOUNT);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_BALANCE_FOR_STORAGE")]
+    fn test_invalid_new_native_storage_not_covered() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context
+            .attached_deposit(TOTAL_AMOUNT.0)
+            .account_balance(TOTAL_AMOUNT.0)
+            .build()
+        );
+        Contract::new_native(accounts(1).into(), accounts(3).into(), U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_BALANCE_FOR_STORAGE")]
+    fn test_invalid_claim_native_storage_not_covered() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(TOTAL_AMOUNT.0).build());
+        let mut contract = Contract::new_native(accounts(1).into(), accounts(3).into(), U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
+
+        // only the grant itself is left on the account
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .account_balance(TOTAL_AMOUNT.0)
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS)
+            .build()
+        );
+        contract.claim_vested(None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TOP_UP_NOT_SUPPORTED_FOR_NATIVE_GRANT")]
+    fn test_invalid_amend_grant_top_up_native() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(TOTAL_AMOUNT.0).build());
+        let mut contract = Contract::new_native(accounts(1).into(), accounts(3).into(), U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.amend_grant(None, None, Some(TOTAL_AMOUNT), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_SUPPORTED_FOR_NATIVE_GRANT")]
+    fn test_invalid_claim_vested_call_native() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(TOTAL_AMOUNT.0).build());
+        let mut contract = Contract::new_native(accounts(1).into(), accounts(3).into(), U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + SIX_MONTHS + 10)
+            .build()
+        );
+        contract.claim_vested_call(accounts(4), None, "".to_string());
+    }
+
+    #[test]
+    fn test_nft_transfer() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1).into(), accounts(3).into(), accounts(2).into(), TOTAL_AMOUNT, U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), false, None);
+        let token = contract.nft_token("0".to_string()).unwrap();
+        assert_eq!(token.owner_id, accounts(3).to_string());
+        assert!(contract.nft_token("1".to_string()).is_none());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(4), "0".to_string(), None, None);
+        assert_eq!(contract.recipient(), accounts(4).to_string());
+        assert_eq!(contract.nft_supply_for_owner(accounts(4)), U128(1));
+        assert_eq!(contract.nft_tokens_for_owner(accounts(3), None, None).len(), 0);
+    }
+
+    #[test]
+    fn test_nft_approve() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1).into(), accounts(3).into(), accounts(2).into(), TOTAL_AMOUNT, U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), false, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(env::storage_byte_cost() * 1000)
+            .build()
+        );
+        assert!(contract.nft_approve("0".to_string(), accounts(4), None).is_none());
+        assert!(contract.nft_is_approved("0".to_string(), accounts(4), Some(0)));
+        assert!(!contract.nft_is_approved("0".to_string(), accounts(4), Some(1)));
+        let approved_account_ids = contract.nft_token("0".to_string()).unwrap().approved_account_ids.unwrap();
+        assert_eq!(approved_account_ids.get(&accounts(4).to_string()), Some(&0));
+
+        // the marketplace sells the grant on behalf of the recipient
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(5), "0".to_string(), Some(0), None);
+        assert_eq!(contract.recipient(), accounts(5).to_string());
+        assert!(!contract.nft_is_approved("0".to_string(), accounts(4), None));
+    }
+
+    #[test]
+    fn test_nft_revoke() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1).into(), accounts(3).into(), accounts(2).into(), TOTAL_AMOUNT, U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), false, None);
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(env::storage_byte_cost() * 1000)
+            .build()
+        );
+        contract.nft_approve("0".to_string(), accounts(4), None);
+        contract.nft_approve("0".to_string(), accounts(5), None);
+        assert!(contract.nft_is_approved("0".to_string(), accounts(5), Some(1)));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_revoke("0".to_string(), accounts(4));
+        assert!(!contract.nft_is_approved("0".to_string(), accounts(4), None));
+        assert!(contract.nft_is_approved("0".to_string(), accounts(5), None));
+        contract.nft_revoke_all("0".to_string());
+        assert!(!contract.nft_is_approved("0".to_string(), accounts(5), None));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_APPROVAL_ID_MISMATCH")]
+    fn test_invalid_nft_transfer_approval_id_mismatch() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1).into(), accounts(3).into(), accounts(2).into(), TOTAL_AMOUNT, U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), false, None);
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(env::storage_byte_cost() * 1000)
+            .build()
+        );
+        contract.nft_approve("0".to_string(), accounts(4), None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(5), "0".to_string(), Some(1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_SENDER_NOT_APPROVED")]
+    fn test_invalid_nft_transfer_sender_not_approved() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1).into(), accounts(3).into(), accounts(2).into(), TOTAL_AMOUNT, U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), false, None);
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(5), "0".to_string(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_GRANT_IS_REVOCABLE")]
+    fn test_invalid_nft_transfer_revocable() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.nft_transfer(accounts(4), "0".to_string(), None, None);
+    }
+
+    #[test]
+    fn test_transfer_grant() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.transfer_grant(accounts(4));
+        assert_eq!(contract.recipient(), accounts(4).to_string());
+    }
+
+    #[test]
+    fn test_transfer_grant_requires_owner_approval() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1).into(), accounts(3).into(), accounts(2).into(), TOTAL_AMOUNT, U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, Some(GrantTransferPolicy::RequiresOwnerApproval));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.transfer_grant(accounts(4));
+        assert_eq!(contract.recipient(), accounts(3).to_string());
+        assert_eq!(contract.pending_grant_transfer(), Some(accounts(4).to_string()));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.approve_grant_transfer();
+        assert_eq!(contract.recipient(), accounts(4).to_string());
+        assert_eq!(contract.pending_grant_transfer(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_GRANT_TRANSFER_FORBIDDEN")]
+    fn test_invalid_transfer_grant_forbidden() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1).into(), accounts(3).into(), accounts(2).into(), TOTAL_AMOUNT, U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, Some(GrantTransferPolicy::Forbidden));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.transfer_grant(accounts(4));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn test_invalid_transfer_grant_without_one_yocto() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .build()
+        );
+        contract.transfer_grant(accounts(4));
+    }
+
+    fn approve_split_code(context: &mut VMContextBuilder, contract: &mut Contract, code: &[u8]) {
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        let mut code_hash = near_sdk::CryptoHash::default();
+        code_hash.copy_from_slice(&env::sha256(code));
+        contract.set_split_code_hash(Some(code_hash.into()));
+    }
+
+    fn split_shares() -> Vec<GrantShare> {
+        vec![
+            GrantShare { recipient: accounts(4), amount: U128::from(TOTAL_AMOUNT.0 / 5 * 3) },
+            GrantShare { recipient: accounts(5), amount: U128::from(TOTAL_AMOUNT.0 / 5 * 2) },
+        ]
+    }
+
+    #[test]
+    fn test_split_grant() {
+        let (mut context, mut contract) = setup_contract();
+        let code = vec![1u8; 100];
+        approve_split_code(&mut context, &mut contract, &code);
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.claim_vested(None);
+        let amount_claimed = TOTAL_AMOUNT.0 * 6 / 24;
+        assert_eq!(contract.amount_claimed(), U128::from(amount_claimed));
+
+        let grants = contract.internal_split_amounts(&split_shares());
+        assert_eq!(grants[0].amount_claimed, U128::from(amount_claimed / 5 * 3));
+        assert_eq!(grants[1].amount_claimed, U128::from(amount_claimed / 5 * 2));
+        assert_eq!(grants[0].start, contract.start());
+        assert_eq!(grants[1].cliff, contract.cliff());
+
+        let deposit = 2 * (100 * env::storage_byte_cost() + utils::SPLIT_GRANT_STORAGE_DEPOSIT);
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff)
+            .attached_deposit(deposit)
+            .build()
+        );
+        let children = contract.split_grant(split_shares(), code.into());
+        assert_eq!(children, vec![
+            format!("split-0-0.{}", accounts(0)),
+            format!("split-0-1.{}", accounts(0)),
+        ]);
+        assert_eq!(contract.is_active, false);
+        assert_eq!(contract.amount(), U128(0));
+        assert_eq!(contract.pending_splits(), 2);
+
+        // the first child is created and funded
+        testing_env!(context.predecessor_account_id(accounts(0)).build(), Default::default(), Default::default(), Default::default(), vec![PromiseResult::Successful(vec![])]);
+        contract.callback_split_grant(children[0].clone(), grants[0].clone(), U128(deposit / 2));
+        testing_env!(context.predecessor_account_id(accounts(0)).build(), Default::default(), Default::default(), Default::default(), vec![PromiseResult::Successful(vec![])]);
+        assert!(contract.callback_fund_split_grant(children[0].clone(), grants[0].clone()));
+        assert_eq!(contract.pending_splits(), 1);
+
+        // the second one fails, its share keeps vesting here
+        testing_env!(context.predecessor_account_id(accounts(0)).block_timestamp(contract.cliff).build(), Default::default(), Default::default(), Default::default(), vec![PromiseResult::Failed]);
+        contract.callback_split_grant(children[1].clone(), grants[1].clone(), U128(deposit / 2));
+        assert_eq!(contract.pending_splits(), 0);
+        assert_eq!(contract.is_active, true);
+        assert_eq!(contract.recipient(), accounts(3).to_string());
+        assert_eq!(contract.amount(), U128::from(TOTAL_AMOUNT.0 / 5 * 2));
+        assert_eq!(contract.amount_claimed(), U128::from(amount_claimed / 5 * 2));
+        assert_eq!(contract.releasable_amount(), U128(0));
+    }
+
+    #[test]
+    fn test_new_split_grant() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(contract.cliff)
+            .attached_deposit(1)
+            .build()
+        );
+        contract.claim_vested(None);
+        let grants = contract.internal_split_amounts(&split_shares());
+
+        testing_env!(context
+            .current_account_id(format!("split-0-0.{}", accounts(0)).try_into().unwrap())
+            .predecessor_account_id(accounts(0))
+            .block_timestamp(contract.cliff + SIX_MONTHS)
+            .build()
+        );
+        let child = Contract::new_split_grant(grants[0].clone());
+        assert_eq!(child.owner(), accounts(1).to_string());
+        assert_eq!(child.recipient(), accounts(4).to_string());
+        assert_eq!(child.amount(), U128::from(TOTAL_AMOUNT.0 / 5 * 3));
+        assert_eq!(child.revocable(), true);
+        // same schedule, so the child vests its share of what the parent would have
+        assert_eq!(child.calculate_amount_vested(), U128::from(TOTAL_AMOUNT.0 / 5 * 3 * 12 / 24));
+        assert_eq!(child.releasable_amount(), U128::from(TOTAL_AMOUNT.0 / 5 * 3 * 6 / 24));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_CREATED_BY_PARENT_GRANT")]
+    fn test_invalid_new_split_grant_not_parent() {
+        let (mut context, contract) = setup_contract();
+        let grants = contract.internal_split_amounts(&split_shares());
+        testing_env!(context
+            .current_account_id(format!("split-0-0.{}", accounts(0)).try_into().unwrap())
+            .predecessor_account_id(accounts(3))
+            .build()
+        );
+        Contract::new_split_grant(grants[0].clone());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_SHARES_DONT_COVER_GRANT")]
+    fn test_invalid_split_grant_shares_dont_cover_grant() {
+        let (mut context, mut contract) = setup_contract();
+        let code = vec![1u8; 100];
+        approve_split_code(&mut context, &mut contract, &code);
+        let mut shares = split_shares();
+        shares[1].amount = U128::from(TOTAL_AMOUNT.0 / 5);
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(2 * (100 * env::storage_byte_cost() + utils::SPLIT_GRANT_STORAGE_DEPOSIT))
+            .build()
+        );
+        contract.split_grant(shares, code.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_CODE_HASH_NOT_APPROVED")]
+    fn test_invalid_split_grant_code_not_approved() {
+        let (mut context, mut contract) = setup_contract();
+        approve_split_code(&mut context, &mut contract, &vec![1u8; 100]);
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(2 * (100 * env::storage_byte_cost() + utils::SPLIT_GRANT_STORAGE_DEPOSIT))
+            .build()
+        );
+        contract.split_grant(split_shares(), vec![2u8; 100].into());
+    }
+
+    #[test]
+    fn test_change_recipient() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .build()
+        );
+
+        contract.change_recipient("changed.near".to_string());
+
+        assert_eq!(contract.recipient(), "changed.near");
+    }
+
+    #[test]
+    fn test_transfer_ownership_to_dao() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.transfer_ownership(accounts(4));
+        assert_eq!(contract.owner(), accounts(4).to_string());
+        assert!(contract.roles_of(accounts(1)).is_empty());
+        let logs = near_sdk::test_utils::get_logs();
+        assert_eq!(logs.iter().filter(|log| log.contains("\"role_revoked\"") && log.contains(accounts(1).as_ref())).count(), 4);
+        assert_eq!(logs.iter().filter(|log| log.contains("\"role_granted\"") && log.contains(accounts(4).as_ref())).count(), 4);
+
+        // the DAO calls through a proposal, without a deposit
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .signer_account_id(accounts(5))
+            .attached_deposit(0)
+            .build()
+        );
+        contract.revoke();
+        assert!(!contract.is_active);
+    }
+
+    #[test]
+    fn test_roles() {
+        let (mut context, mut contract) = setup_contract();
+        assert_eq!(contract.roles_of(accounts(1)), vec![Role::Admin, Role::Revoker, Role::RecipientManager, Role::Pauser]);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.grant_role(accounts(4), Role::RecipientManager);
+        contract.revoke_role(accounts(1), Role::RecipientManager);
+        assert!(contract.has_role(accounts(4), Role::RecipientManager));
+        assert!(!contract.has_role(accounts(1), Role::RecipientManager));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .build()
+        );
+        contract.change_recipient("changed.near".to_string());
+        assert_eq!(contract.recipient(), "changed.near");
+
+        contract.renounce_role(Role::RecipientManager);
+        assert!(contract.roles_of(accounts(4)).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_MISSING_ROLE")]
+    fn test_invalid_revoke_without_revoker_role() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.grant_role(accounts(4), Role::Revoker);
+        contract.revoke_role(accounts(1), Role::Revoker);
+
+        contract.revoke();
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_MISSING_ROLE")]
+    fn test_invalid_grant_role_caller_not_admin() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.grant_role(accounts(3), Role::Revoker);
+    }
+
+    #[test]
+    fn test_multisig_change_recipient() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_multisig(vec![accounts(1), accounts(4), accounts(5)], 2);
+
+        let proposal_id = contract.add_multisig_proposal(MultisigProposalKind::ChangeRecipient { recipient: "changed.near".to_string() });
+        assert_eq!(contract.multisig_proposal(proposal_id).unwrap().confirmations, vec![accounts(1).to_string()]);
+        assert_eq!(contract.recipient(), accounts(3).to_string());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .build()
+        );
+        assert!(contract.confirm_multisig_proposal(proposal_id));
+        assert_eq!(contract.recipient(), "changed.near");
+        assert!(contract.multisig_proposal(proposal_id).is_none());
+
+        let proposal_id = contract.add_multisig_proposal(MultisigProposalKind::TransferOwnership { owner: accounts(4).to_string() });
+        testing_env!(context
+            .predecessor_account_id(accounts(5))
+            .build()
+        );
+        contract.confirm_multisig_proposal(proposal_id);
+        assert_eq!(contract.owner(), accounts(4).to_string());
+        assert!(contract.has_role(accounts(4), Role::Revoker));
+        assert!(contract.roles_of(accounts(1)).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_MULTISIG_REQUIRED")]
+    fn test_invalid_revoke_multisig_enabled() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_multisig(vec![accounts(1), accounts(4)], 2);
+
+        contract.revoke();
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_MULTISIG_REQUIRED")]
+    fn test_invalid_pause_vesting_multisig_enabled() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_multisig(vec![accounts(1), accounts(4)], 2);
+
+        contract.pause_vesting();
+    }
+
+    #[test]
+    fn test_multisig_pause_vesting() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + ONE_MONTH * 12)
+            .build()
+        );
+        contract.set_multisig(vec![accounts(1), accounts(4)], 2);
+        let proposal_id = contract.add_multisig_proposal(MultisigProposalKind::PauseVesting);
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .build()
+        );
+        contract.confirm_multisig_proposal(proposal_id);
+        assert!(contract.is_vesting_paused());
+
+        let proposal_id = contract.add_multisig_proposal(MultisigProposalKind::SetAccelerator { accelerator: Some(accounts(5).to_string()) });
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .build()
+        );
+        contract.confirm_multisig_proposal(proposal_id);
+        assert_eq!(contract.accelerator(), Some(accounts(5).to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PROPOSAL_EXPIRED")]
+    fn test_invalid_confirm_expired_multisig_proposal() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021)
+            .build()
+        );
+        contract.set_multisig(vec![accounts(1), accounts(4)], 2);
+        let proposal_id = contract.add_multisig_proposal(MultisigProposalKind::Revoke);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .block_timestamp(JUNE_1_2021 + 7 * 86400000000000)
+            .build()
+        );
+        contract.confirm_multisig_proposal(proposal_id);
+    }
+
+    #[test]
+    fn test_revoke_by_contract_owner_without_deposit() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1).into(), accounts(3).into(), accounts(2).into(), TOTAL_AMOUNT, U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
+
+        // e.g. a DAO executing a proposal signed by one of its members
+        testing_env!(context
+            .signer_account_id(accounts(4))
+            .block_timestamp(JUNE_1_2021 + SIX_MONTHS + 10)
+            .build()
+        );
+        contract.revoke();
+        assert!(!contract.get_vesting_info().is_active);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn test_invalid_revoke_by_owner_key_without_deposit() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .signer_account_id(accounts(1))
+            .build()
+        );
+        contract.revoke();
+    }
+
+    fn borsh_string(value: &str) -> Vec<u8> {
+        let mut bytes = (value.len() as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(value.as_bytes());
+        bytes
+    }
+
+    // state written by the first deployed version
+    // a V1 revoke zeroed the amount and the schedule
+    fn contract_v1_state(amount_claimed: u128, is_active: bool) -> Vec<u8> {
+        let (amount, start, duration, cliff) = if is_active {
+            (TOTAL_AMOUNT.0, JUNE_1_2021, TWO_YEARS, JUNE_1_2021 + SIX_MONTHS)
+        } else {
+            (0, 0, 0, 0)
+        };
+        let mut state = vec![];
+        state.extend(borsh_string("bob"));
+        state.extend(borsh_string("danny"));
+        state.extend(borsh_string("charlie"));
+        state.extend_from_slice(&amount.to_le_bytes());
+        state.extend_from_slice(&amount_claimed.to_le_bytes());
+        state.extend_from_slice(&start.to_le_bytes());
+        state.extend_from_slice(&duration.to_le_bytes());
+        state.extend_from_slice(&cliff.to_le_bytes());
+        state.push(1);
+        state.push(is_active as u8);
+        state
+    }
+
+    #[test]
+    fn test_migrate_from_v1() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(JUNE_1_2021 + TWO_YEARS / 2).build());
+        env::storage_write(b"STATE", &contract_v1_state(TOTAL_AMOUNT.0 / 4, true));
+
+        let contract = Contract::migrate();
+        let vesting_info = contract.get_vesting_info();
+        assert_eq!(vesting_info.owner, accounts(1).to_string());
+        assert_eq!(vesting_info.recipient, accounts(3).to_string());
+        assert_eq!(vesting_info.token, Some(accounts(2).to_string()));
+        assert_eq!(vesting_info.amount, TOTAL_AMOUNT);
+        assert_eq!(vesting_info.amount_claimed, U128(TOTAL_AMOUNT.0 / 4));
+        assert_eq!(vesting_info.cliff, U64::from(JUNE_1_2021 + SIX_MONTHS));
+        assert_eq!(vesting_info.curve, VestingCurve::Monthly);
+        assert!(vesting_info.revocable);
+        assert!(vesting_info.is_active);
+        assert_eq!(contract.transfer_policy(), GrantTransferPolicy::RequiresOwnerApproval);
+        assert!(contract.has_role(accounts(1), Role::Revoker));
+        assert_eq!(contract.voting_power(accounts(3), None).vested_unclaimed, U128(TOTAL_AMOUNT.0 / 4));
+        // nothing new was minted or granted
+        assert!(near_sdk::test_utils::get_logs().is_empty());
+    }
+
+    #[test]
+    fn test_migrate_revoked_from_v1() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(JUNE_1_2021 + TWO_YEARS / 2).build());
+        env::storage_write(b"STATE", &contract_v1_state(TOTAL_AMOUNT.0 / 4, false));
+
+        let contract = Contract::migrate();
+        assert!(!contract.is_active);
+        assert_eq!(contract.amount(), U128(TOTAL_AMOUNT.0 / 4));
+        assert_eq!(contract.amount_claimed(), U128(TOTAL_AMOUNT.0 / 4));
+        assert_eq!(contract.releasable_amount(), U128(0));
+        assert!(contract.nft_token("0".to_string()).is_some());
+        assert_eq!(contract.voting_power(accounts(3), None).weighted_total, U128(0));
+        assert!(near_sdk::test_utils::get_logs().is_empty());
+    }
+
+    #[test]
+    fn test_migrate_current_state() {
+        let (_, contract) = setup_contract();
+        env::state_write(&contract);
+
+        assert_eq!(env::storage_read(b"STATE").unwrap()[0], CURRENT_STATE_VERSION);
+
+        let contract = Contract::migrate();
+        assert_eq!(contract.recipient(), accounts(3).to_string());
+        assert_eq!(contract.checkpoints(None, None).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_UNKNOWN_STATE_VERSION")]
+    fn test_invalid_migrate_unknown_state_version() {
+        let (_, contract) = setup_contract();
+        let mut state = contract.try_to_vec().unwrap();
+        state[0] = CURRENT_STATE_VERSION + 1;
+        env::storage_write(b"STATE", &state);
+
+        Contract::migrate();
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_UNKNOWN_STATE_LAYOUT")]
+    fn test_invalid_migrate_unknown_state() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut state = contract_v1_state(0, true);
+        state.push(0);
+        env::storage_write(b"STATE", &state);
+
+        Contract::migrate();
+    }
+
+    #[test]
+    fn test_stage_and_deploy_upgrade() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(10u128.pow(24))
+            .block_timestamp(JUNE_1_2021)
+            .input(b"new code".to_vec())
+            .build()
+        );
+        let upgrade = contract.stage_upgrade();
+        assert_eq!(upgrade.deployable_at, U64::from(JUNE_1_2021 + 7 * 86400000000000));
+        let mut code_hash = [0u8; 32];
+        code_hash.copy_from_slice(&env::sha256(b"new code"));
+        assert_eq!(contract.staged_upgrade().unwrap().code_hash, code_hash.into());
+
+        testing_env!(context
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + 7 * 86400000000000)
+            .input(vec![])
+            .build()
+        );
+        contract.deploy_upgrade();
+        assert!(contract.staged_upgrade().is_none());
+    }
+
+    #[test]
+    fn test_multisig_upgrade() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021)
+            .build()
+        );
+        contract.set_multisig(vec![accounts(1), accounts(4)], 2);
+        let mut code_hash = [0u8; 32];
+        code_hash.copy_from_slice(&env::sha256(b"new code"));
+        let proposal_id = contract.add_multisig_proposal(MultisigProposalKind::StageUpgrade { code_hash: code_hash.into() });
+
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .build()
+        );
+        contract.confirm_multisig_proposal(proposal_id);
+        assert_eq!(contract.approved_upgrade(), Some(code_hash.into()));
+
+        testing_env!(context
+            .attached_deposit(10u128.pow(24))
+            .input(b"new code".to_vec())
+            .build()
+        );
+        contract.stage_approved_upgrade();
+        assert!(contract.approved_upgrade().is_none());
+        assert_eq!(contract.staged_upgrade().unwrap().code_hash, code_hash.into());
+        assert_eq!(contract.staged_upgrade().unwrap().staged_by, accounts(4).to_string());
+
+        testing_env!(context
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + 7 * 86400000000000)
+            .build()
+        );
+        let proposal_id = contract.add_multisig_proposal(MultisigProposalKind::DeployUpgrade);
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .build()
+        );
+        contract.confirm_multisig_proposal(proposal_id);
+        assert!(contract.staged_upgrade().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_MULTISIG_REQUIRED")]
+    fn test_invalid_stage_upgrade_multisig_enabled() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_multisig(vec![accounts(1), accounts(4)], 2);
+
+        testing_env!(context
+            .attached_deposit(10u128.pow(24))
+            .input(b"new code".to_vec())
+            .build()
+        );
+        contract.stage_upgrade();
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_CODE_HASH_NOT_APPROVED")]
+    fn test_invalid_stage_approved_upgrade_other_code() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build()
+        );
+        contract.set_multisig(vec![accounts(1)], 1);
+        let mut code_hash = [0u8; 32];
+        code_hash.copy_from_slice(&env::sha256(b"new code"));
+        contract.add_multisig_proposal(MultisigProposalKind::StageUpgrade { code_hash: code_hash.into() });
+
+        testing_env!(context
+            .attached_deposit(10u128.pow(24))
+            .input(b"other code".to_vec())
+            .build()
+        );
+        contract.stage_approved_upgrade();
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_UPGRADE_TIMELOCKED")]
+    fn test_invalid_deploy_upgrade_before_delay() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(10u128.pow(24))
+            .block_timestamp(JUNE_1_2021)
+            .input(b"new code".to_vec())
+            .build()
+        );
+        contract.stage_upgrade();
+
+        testing_env!(context
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + 7 * 86400000000000 - 1)
+            .input(vec![])
+            .build()
+        );
+        contract.deploy_upgrade();
+    }
+
+    #[test]
+    fn test_finalize_native() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.attached_deposit(TOTAL_AMOUNT.0).build());
+        let mut contract = Contract::new_native(accounts(1).into(), accounts(3).into(), U64::from(JUNE_1_2021), U64::from(TWO_YEARS), U64::from(SIX_MONTHS), true, None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS)
+            .build()
+        );
+        contract.claim_vested(None);
+        assert_eq!(contract.amount_claimed(), contract.amount());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .build()
+        );
+        contract.finalize();
+        let logs = near_sdk::test_utils::get_logs();
+        assert!(logs.iter().any(|log| log.contains("\"nft_burn\"")));
+        assert!(logs.iter().any(|log| log.contains("\"grant_finalized\"")));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_GRANT_NOT_FULLY_CLAIMED")]
+    fn test_invalid_finalize_not_fully_claimed() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS / 2)
+            .build()
+        );
+        contract.claim_vested(None);
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .build()
+        );
+        contract.finalize();
+    }
+
+    #[test]
+    fn test_finalize_after_revoke() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS / 2)
+            .build()
+        );
+        contract.revoke();
+        assert!(!contract.is_active);
+        assert_eq!(contract.amount(), U128(TOTAL_AMOUNT.0 / 2));
+        assert_eq!(contract.amount_claimed(), contract.amount());
+        contract.finalize();
+        // the grant is only closed once the token balance is checked
+        assert!(!near_sdk::test_utils::get_logs().iter().any(|log| log.contains("\"grant_finalized\"")));
+
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            vec![PromiseResult::Successful(b"\"0\"".to_vec())]
+        );
+        contract.callback_finalize();
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains("\"grant_finalized\"")));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TOKENS_LEFT_IN_CONTRACT")]
+    fn test_invalid_finalize_tokens_left_in_contract() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .block_timestamp(JUNE_1_2021 + TWO_YEARS / 2)
+            .build()
+        );
+        contract.revoke();
+        contract.finalize();
+
+        // e.g. a transfer of the revoke that failed and came back
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            vec![PromiseResult::Successful(b"\"1000\"".to_vec())]
+        );
+        contract.callback_finalize();
     }
 }
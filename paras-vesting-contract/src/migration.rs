@@ -0,0 +1,131 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::roles::{Role, RoleSet};
+use crate::{Contract, GrantTransferPolicy, GrantType, StorageKey, VestingAsset, VestingCurve};
+
+// Stored as the first byte of the state. Bump it, and add the layout being replaced
+// to VersionedContract, before changing the fields of Contract.
+//
+// Borsh writes an enum as its variant index (a u8) followed by the fields of the variant,
+// and a struct as its fields in order. A state that starts with a u8 version is laid out
+// the same way as a Borsh enum with that tag, without a match on every state access.
+pub const CURRENT_STATE_VERSION: u8 = 2;
+
+// Layout of the first deployed version, never change it
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct ContractV1 {
+    pub owner: AccountId,
+    pub recipient: AccountId,
+    pub token: AccountId,
+    pub amount: u128,
+    pub amount_claimed: u128,
+    pub start: u64,
+    pub duration: u64,
+    pub cliff: u64,
+    pub revocable: bool,
+    pub is_active: bool,
+}
+
+// Every layout the contract state can be stored with
+pub enum VersionedContract {
+    V1(ContractV1),
+    Current(Contract),
+}
+
+impl VersionedContract {
+    pub fn from_state(state: &[u8]) -> Self {
+        match state_version(state) {
+            1 => VersionedContract::V1(ContractV1::try_from_slice(state).expect("ERR_UNKNOWN_STATE_LAYOUT")),
+            CURRENT_STATE_VERSION => VersionedContract::Current(Contract::try_from_slice(state).expect("ERR_UNKNOWN_STATE_LAYOUT")),
+            _ => env::panic(b"ERR_UNKNOWN_STATE_VERSION"),
+        }
+    }
+
+    pub fn into_current(self) -> Contract {
+        match self {
+            VersionedContract::V1(contract) => Contract::from_v1(contract),
+            VersionedContract::Current(contract) => contract,
+        }
+    }
+}
+
+// V1 has no version and starts with the length of the owner, a little endian u32
+// below 256. Versioned states have the owner length right after the version, and
+// account ids are at least 2 characters long, so the second byte is never 0.
+fn state_version(state: &[u8]) -> u8 {
+    assert!(state.len() > 1, "ERR_UNKNOWN_STATE_LAYOUT");
+    if state[1] == 0 { 1 } else { state[0] }
+}
+
+#[near_bindgen]
+impl Contract {
+    // deploy the new code and call migrate in the same transaction
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = env::storage_read(b"STATE").expect("ERR_CONTRACT_NOT_INITIALIZED");
+        VersionedContract::from_state(&state).into_current()
+    }
+}
+
+impl Contract {
+    // New features start disabled, recipient initiated transfers didn't exist in V1.
+    // The grant and the roles already exist, so no nft_mint or role_granted events.
+    fn from_v1(contract: ContractV1) -> Self {
+        // a V1 revoke zeroed the amount, grants now end at what was claimed
+        let amount = if contract.is_active { contract.amount } else { contract.amount_claimed };
+        let mut this = Self {
+            state_version: CURRENT_STATE_VERSION,
+            owner: contract.owner,
+            recipient: contract.recipient,
+            asset: VestingAsset::FungibleToken(contract.token),
+            amount: amount,
+            amount_claimed: contract.amount_claimed,
+            start: contract.start,
+            duration: contract.duration,
+            cliff: contract.cliff,
+            revocable: contract.revocable,
+            is_active: contract.is_active,
+            paused_intervals: Vec::new(),
+            curve: VestingCurve::Monthly,
+            pending_amendment: None,
+            accelerator: None,
+            amount_accelerated: 0,
+            grant_type: GrantType::Time,
+            approver: None,
+            milestones: Vec::new(),
+            push_min_interval: 0,
+            last_pushed_at: 0,
+            push_enabled: false,
+            keeper_reward: 0,
+            keeper_fund: 0,
+            claim_operators: UnorderedSet::new(StorageKey::ClaimOperators),
+            transfer_policy: GrantTransferPolicy::RequiresOwnerApproval,
+            pending_grant_transfer: None,
+            extra_tokens: Vec::new(),
+            staking_pools: UnorderedSet::new(StorageKey::StakingPools),
+            staking_pool: None,
+            staked_principal: 0,
+            voting_delegate: None,
+            unvested_voting_weight: 100,
+            checkpoints: Vector::new(StorageKey::Checkpoints),
+            guardian: None,
+            paused_until: 0,
+            pause_armed: true,
+            roles: LookupMap::new(StorageKey::Roles),
+            multisig_signers: vec![],
+            multisig_threshold: 0,
+            multisig_proposals: UnorderedMap::new(StorageKey::MultisigProposals),
+            next_multisig_proposal_id: 0,
+        };
+        let mut roles = RoleSet::default();
+        for role in [Role::Admin, Role::Revoker, Role::RecipientManager, Role::Pauser].iter() {
+            roles.insert(*role);
+        }
+        this.roles.insert(&this.owner.clone(), &roles);
+        this.internal_checkpoint();
+        this
+    }
+}
@@ -0,0 +1,188 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64, ValidAccountId};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId, Promise};
+
+use crate::utils::assert_one_yocto_or_contract_caller;
+use crate::{Contract, GrantTransferPolicy, GrantType, VestingAsset};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MilestoneArgs {
+    pub amount: U128,
+    // after expiry an unapproved tranche can be reclaimed by the owner
+    pub expires_at: Option<U64>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum MilestoneStatus {
+    Pending,
+    Approved,
+    Reclaimed,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Milestone {
+    pub amount: U128,
+    pub expires_at: Option<U64>,
+    pub status: MilestoneStatus,
+}
+
+#[near_bindgen]
+impl Contract {
+    #[init]
+    pub fn new_milestone_grant(
+        owner: ValidAccountId,
+        recipient: ValidAccountId,
+        token: ValidAccountId,
+        approver: ValidAccountId,
+        milestones: Vec<MilestoneArgs>,
+        revocable: bool,
+        transfer_policy: Option<GrantTransferPolicy>,
+    ) -> Self {
+        let milestones = internal_milestones(milestones);
+        let amount = milestones.iter().fold(0u128, |total, milestone| {
+            total.checked_add(milestone.amount.0).expect("ERR_INTEGER_OVERFLOW")
+        });
+
+        let mut this = Self::internal_new(
+            owner.into(),
+            recipient.into(),
+            VestingAsset::FungibleToken(token.into()),
+            amount,
+            env::block_timestamp(),
+            0,
+            env::block_timestamp(),
+            revocable,
+            transfer_policy.unwrap_or(GrantTransferPolicy::Allowed),
+            GrantType::Milestone,
+        );
+        this.approver = Some(approver.into());
+        this.milestones = milestones;
+        this
+    }
+
+    // time curve combined with a milestone gate, amount is the sum of the tranches
+    #[init]
+    pub fn new_hybrid_grant(
+        owner: ValidAccountId,
+        recipient: ValidAccountId,
+        token: ValidAccountId,
+        start: U64,
+        duration: U64,
+        cliff_duration: U64,
+        approver: ValidAccountId,
+        milestones: Vec<MilestoneArgs>,
+        revocable: bool,
+        transfer_policy: Option<GrantTransferPolicy>,
+    ) -> Self {
+        let milestones = internal_milestones(milestones);
+        let amount = milestones.iter().fold(0u128, |total, milestone| {
+            total.checked_add(milestone.amount.0).expect("ERR_INTEGER_OVERFLOW")
+        });
+
+        let mut this = Self::new(owner, recipient, token, amount.into(), start, duration, cliff_duration, revocable, transfer_policy);
+        this.grant_type = GrantType::Hybrid;
+        this.approver = Some(approver.into());
+        this.milestones = milestones;
+        this
+    }
+
+    pub fn approver(&self) -> Option<AccountId> {
+        self.approver.clone()
+    }
+
+    pub fn milestones(&self) -> Vec<Milestone> {
+        self.milestones.clone()
+    }
+
+    pub fn approve_milestone(&mut self, milestone_id: u64) {
+        assert_eq!(Some(env::predecessor_account_id()), self.approver, "ERR_CALLER_NOT_APPROVER");
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+
+        let milestone = self.milestones.get_mut(milestone_id as usize).expect("ERR_MILESTONE_NOT_FOUND");
+        assert_eq!(milestone.status, MilestoneStatus::Pending, "ERR_MILESTONE_NOT_PENDING");
+        assert!(
+            milestone.expires_at.map_or(true, |expires_at| env::block_timestamp() < expires_at.0),
+            "ERR_MILESTONE_EXPIRED"
+        );
+
+        milestone.status = MilestoneStatus::Approved;
+        self.internal_checkpoint();
+    }
+
+    // owner takes back an expired tranche that was never approved
+    #[payable]
+    pub fn reclaim_expired_milestone(&mut self, milestone_id: u64) -> Promise {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+        self.assert_multisig_disabled();
+
+        self.internal_reclaim_expired_milestone(milestone_id)
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_reclaim_expired_milestone(&mut self, milestone_id: u64) -> Promise {
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+
+        let milestone = self.milestones.get_mut(milestone_id as usize).expect("ERR_MILESTONE_NOT_FOUND");
+        assert_eq!(milestone.status, MilestoneStatus::Pending, "ERR_MILESTONE_NOT_PENDING");
+        assert!(
+            milestone.expires_at.map_or(false, |expires_at| env::block_timestamp() >= expires_at.0),
+            "ERR_MILESTONE_NOT_EXPIRED"
+        );
+
+        milestone.status = MilestoneStatus::Reclaimed;
+        let amount = milestone.amount.0;
+        assert!(self.internal_liquid_balance() >= amount, "ERR_AMOUNT_EXCEEDS_LIQUID_BALANCE");
+        // accelerated amounts are vested and can't be reclaimed anymore
+        assert!(
+            self.amount - self.internal_calculate_amount_vested() >= amount,
+            "ERR_MILESTONE_ALREADY_VESTED"
+        );
+        let amount_vested_before = self.internal_calculate_amount_vested();
+        self.amount = self.amount.checked_sub(amount).expect("ERR_INTEGER_OVERFLOW");
+        // only the unvested part goes back
+        assert!(
+            self.internal_calculate_amount_vested() >= amount_vested_before,
+            "ERR_MILESTONE_ALREADY_VESTED"
+        );
+        self.internal_checkpoint();
+
+        self.internal_transfer_asset(self.owner(), amount)
+    }
+
+    // the time curve of a hybrid grant runs on the sum of all its tranches,
+    // reclaiming a tranche doesn't slow it down
+    pub(crate) fn internal_calculate_amount_on_curve(&self) -> u128 {
+        match self.grant_type {
+            GrantType::Hybrid => self.milestones.iter().fold(0, |total, milestone| total + milestone.amount.0),
+            _ => self.amount,
+        }
+    }
+
+    pub(crate) fn internal_calculate_amount_approved(&self) -> u128 {
+        self.milestones
+            .iter()
+            .filter(|milestone| milestone.status == MilestoneStatus::Approved)
+            .fold(0, |total, milestone| total + milestone.amount.0)
+    }
+}
+
+pub(crate) fn internal_milestones(milestones: Vec<MilestoneArgs>) -> Vec<Milestone> {
+    assert!(!milestones.is_empty(), "ERR_NO_MILESTONES");
+    milestones
+        .into_iter()
+        .map(|milestone| {
+            assert!(milestone.amount.0 > 0, "ERR_MILESTONE_AMOUNT_IS_ZERO");
+            Milestone {
+                amount: milestone.amount,
+                expires_at: milestone.expires_at,
+                status: MilestoneStatus::Pending,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,228 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64, ValidAccountId};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId};
+
+use crate::utils::{ext_fungible_token, ext_self, is_promise_success, mul_div, GAS_FOR_FT_TRANSFER, GAS_FOR_RESOLVE_CLAIM};
+use crate::{assert_valid_schedule, Contract, GrantTransferPolicy, GrantType, VestingAsset};
+
+// keeps the number of transfers of a single claim within the gas limit
+pub const MAX_EXTRA_TOKENS: usize = 4;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenAmount {
+    pub token: ValidAccountId,
+    pub amount: U128,
+}
+
+// token vesting alongside the main token, pro rata to the amount vested of the main token
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenAllocation {
+    pub token: AccountId,
+    pub amount: U128,
+    pub amount_claimed: U128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenAllocationInfo {
+    pub token: Option<AccountId>,
+    pub amount: U128,
+    pub amount_claimed: U128,
+    pub amount_vested: U128,
+    pub releasable_amount: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    // the first token is the main token of the grant, the others share its schedule
+    #[init]
+    pub fn new_multi_token(
+        owner: ValidAccountId,
+        recipient: ValidAccountId,
+        tokens: Vec<TokenAmount>,
+        start: U64,
+        duration: U64,
+        cliff_duration: U64,
+        revocable: bool,
+        transfer_policy: Option<GrantTransferPolicy>,
+    ) -> Self {
+        assert!(!tokens.is_empty(), "ERR_NO_TOKENS");
+        assert!(tokens.len() <= MAX_EXTRA_TOKENS + 1, "ERR_TOO_MANY_TOKENS");
+        assert_valid_schedule(start.0, duration.0, cliff_duration.0);
+        for (index, token_amount) in tokens.iter().enumerate() {
+            assert!(token_amount.amount.0 > 0, "ERR_AMOUNT_IS_ZERO");
+            assert!(
+                tokens[..index].iter().all(|other| other.token.as_ref() != token_amount.token.as_ref()),
+                "ERR_DUPLICATE_TOKEN"
+            );
+        }
+
+        let mut tokens = tokens.into_iter();
+        let main_token = tokens.next().unwrap();
+        let mut this = Self::internal_new(
+            owner.into(),
+            recipient.into(),
+            VestingAsset::FungibleToken(main_token.token.into()),
+            main_token.amount.0,
+            start.0,
+            duration.0,
+            start.0.checked_add(cliff_duration.into()).expect("ERR_INTEGER_OVERFLOW"),
+            revocable,
+            transfer_policy.unwrap_or(GrantTransferPolicy::Allowed),
+            GrantType::Time,
+        );
+        this.extra_tokens = tokens
+            .map(|token_amount| TokenAllocation {
+                token: token_amount.token.into(),
+                amount: token_amount.amount,
+                amount_claimed: U128(0),
+            })
+            .collect();
+        this
+    }
+
+    pub fn extra_tokens(&self) -> Vec<TokenAllocation> {
+        self.extra_tokens.clone()
+    }
+
+    // every token of the grant, the main token first
+    pub fn token_allocations(&self) -> Vec<TokenAllocationInfo> {
+        let mut token_allocations = vec![TokenAllocationInfo {
+            token: self.token(),
+            amount: self.amount(),
+            amount_claimed: self.amount_claimed(),
+            amount_vested: self.calculate_amount_vested(),
+            releasable_amount: self.releasable_amount(),
+        }];
+        for token_index in 0..self.extra_tokens.len() {
+            let extra_token = &self.extra_tokens[token_index];
+            token_allocations.push(TokenAllocationInfo {
+                token: Some(extra_token.token.clone()),
+                amount: extra_token.amount,
+                amount_claimed: extra_token.amount_claimed,
+                amount_vested: self.internal_extra_token_amount_vested(token_index).into(),
+                releasable_amount: self.internal_extra_token_releasable_amount(token_index).into(),
+            });
+        }
+        token_allocations
+    }
+
+    // extra tokens left behind by the claims of the main token (e.g. a rolled back transfer)
+    #[payable]
+    pub fn claim_extra_tokens(&mut self) {
+        assert_one_yocto();
+        let predecessor_account_id = env::predecessor_account_id();
+        assert!(
+            predecessor_account_id == self.recipient() || self.claim_operators.contains(&predecessor_account_id),
+            "ERR_CALLER_NOT_RECIPIENT_OR_OPERATOR"
+        );
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        self.assert_not_paused();
+        assert!(
+            (0..self.extra_tokens.len()).any(|token_index| self.internal_extra_token_claimable_amount(token_index) > 0),
+            "ERR_NO_VESTED_AMOUNT_ARE_DUE"
+        );
+
+        self.internal_transfer_extra_tokens(&self.recipient());
+    }
+
+    // roll back the claim of an extra token if its transfer failed
+    #[private]
+    pub fn callback_claim_extra_token(&mut self, token_index: u64, amount: U128) -> U128 {
+        if is_promise_success() {
+            amount
+        } else {
+            let extra_token = &mut self.extra_tokens[token_index as usize];
+            extra_token.amount_claimed = U128(extra_token.amount_claimed.0.checked_sub(amount.0).expect("ERR_INTEGER_OVERFLOW"));
+            U128(0)
+        }
+    }
+}
+
+impl Contract {
+    fn internal_extra_token_amount_vested(&self, token_index: usize) -> u128 {
+        if self.amount == 0 {
+            return 0;
+        }
+        mul_div(self.extra_tokens[token_index].amount.0, self.internal_calculate_amount_vested(), self.amount)
+    }
+
+    fn internal_extra_token_releasable_amount(&self, token_index: usize) -> u128 {
+        self.internal_extra_token_amount_vested(token_index).saturating_sub(self.extra_tokens[token_index].amount_claimed.0)
+    }
+
+    // the claimed part of an extra token follows the claimed part of the main token
+    fn internal_extra_token_claimable_amount(&self, token_index: usize) -> u128 {
+        if self.amount == 0 {
+            return 0;
+        }
+        let amount_claimable = mul_div(self.extra_tokens[token_index].amount.0, self.amount_claimed, self.amount);
+        std::cmp::min(amount_claimable, self.internal_extra_token_amount_vested(token_index))
+            .saturating_sub(self.extra_tokens[token_index].amount_claimed.0)
+    }
+
+    // runs once amount_claimed of the main token is updated, one transfer and callback per token
+    pub(crate) fn internal_transfer_extra_tokens(&mut self, receiver_id: &AccountId) {
+        for token_index in 0..self.extra_tokens.len() {
+            let releasable = self.internal_extra_token_claimable_amount(token_index);
+            if releasable == 0 {
+                continue;
+            }
+
+            let extra_token = &mut self.extra_tokens[token_index];
+            extra_token.amount_claimed = U128(extra_token.amount_claimed.0.checked_add(releasable).expect("ERR_INTEGER_OVERFLOW"));
+
+            ext_fungible_token::ft_transfer(
+                receiver_id.clone(),
+                releasable.into(),
+                None,
+                &extra_token.token,
+                1,
+                GAS_FOR_FT_TRANSFER
+            ).then(ext_self::callback_claim_extra_token(
+                token_index as u64,
+                releasable.into(),
+                &env::current_account_id(),
+                0,
+                GAS_FOR_RESOLVE_CLAIM
+            ));
+        }
+    }
+
+    // vested part goes to the recipient, the rest back to the owner, must run before the main token is settled
+    pub(crate) fn internal_revoke_extra_tokens(&mut self) {
+        for token_index in 0..self.extra_tokens.len() {
+            let amount_vested = self.internal_extra_token_amount_vested(token_index);
+            let releasable = self.internal_extra_token_releasable_amount(token_index);
+            let extra_token = &mut self.extra_tokens[token_index];
+            let amount_not_vested = extra_token.amount.0.checked_sub(amount_vested).expect("ERR_INTEGER_OVERFLOW");
+
+            extra_token.amount_claimed = U128(extra_token.amount_claimed.0.checked_add(releasable).expect("ERR_INTEGER_OVERFLOW"));
+            extra_token.amount = extra_token.amount_claimed;
+
+            if releasable > 0 {
+                ext_fungible_token::ft_transfer(
+                    self.recipient.clone(),
+                    releasable.into(),
+                    None,
+                    &extra_token.token,
+                    1,
+                    GAS_FOR_FT_TRANSFER
+                );
+            }
+            if amount_not_vested > 0 {
+                ext_fungible_token::ft_transfer(
+                    self.owner.clone(),
+                    amount_not_vested.into(),
+                    None,
+                    &extra_token.token,
+                    1,
+                    GAS_FOR_FT_TRANSFER
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{Base58CryptoHash, U64, ValidAccountId};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::amendment::GrantAmendment;
+use crate::utils::{assert_one_yocto_or_contract_caller, MAX_MULTISIG_SIGNERS, MULTISIG_PROPOSAL_DURATION};
+use crate::Contract;
+
+// Once signers are configured, revoke, recipient and ownership changes, amendments, vesting
+// pauses, milestone reclaims, the accelerator and upgrades only run through proposals confirmed
+// by threshold signers. Proposals are removed once executed or cancelled.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum MultisigProposalKind {
+    Revoke,
+    ChangeRecipient { recipient: AccountId },
+    TransferOwnership { owner: AccountId },
+    AmendGrant { amendment: GrantAmendment },
+    // replaces the signers and drops every pending proposal
+    SetMultisig { signers: Vec<AccountId>, threshold: u8 },
+    // approves the code a signer can then stage with stage_approved_upgrade
+    StageUpgrade { code_hash: Base58CryptoHash },
+    DeployUpgrade,
+    PauseVesting,
+    ResumeVesting,
+    ReclaimExpiredMilestone { milestone_id: u64 },
+    SetAccelerator { accelerator: Option<AccountId> },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MultisigProposal {
+    pub id: u64,
+    pub proposer: AccountId,
+    pub kind: MultisigProposalKind,
+    pub confirmations: Vec<AccountId>,
+    pub expires_at: U64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MultisigConfig {
+    pub signers: Vec<AccountId>,
+    pub threshold: u8,
+}
+
+#[near_bindgen]
+impl Contract {
+    // can only be set once by the owner, later changes need a SetMultisig proposal
+    #[payable]
+    pub fn set_multisig(&mut self, signers: Vec<ValidAccountId>, threshold: u8) {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+        assert!(!self.is_multisig_enabled(), "ERR_MULTISIG_ALREADY_CONFIGURED");
+
+        self.internal_set_multisig(signers.into_iter().map(|signer| signer.into()).collect(), threshold);
+    }
+
+    pub fn multisig_config(&self) -> MultisigConfig {
+        MultisigConfig {
+            signers: self.multisig_signers.clone(),
+            threshold: self.multisig_threshold,
+        }
+    }
+
+    pub fn multisig_proposal(&self, proposal_id: u64) -> Option<MultisigProposal> {
+        self.multisig_proposals.get(&proposal_id)
+    }
+
+    pub fn multisig_proposals(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<MultisigProposal> {
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(self.multisig_proposals.len());
+        self.multisig_proposals
+            .values()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    // the proposer confirms right away, returns the proposal id
+    #[payable]
+    pub fn add_multisig_proposal(&mut self, kind: MultisigProposalKind) -> u64 {
+        assert_one_yocto_or_contract_caller();
+        self.assert_multisig_signer();
+        match &kind {
+            MultisigProposalKind::ChangeRecipient { recipient } => assert_valid_account_id(recipient),
+            MultisigProposalKind::TransferOwnership { owner } => assert_valid_account_id(owner),
+            MultisigProposalKind::SetMultisig { signers, threshold } => assert_valid_multisig(signers, *threshold),
+            MultisigProposalKind::SetAccelerator { accelerator: Some(accelerator) } => assert_valid_account_id(accelerator),
+            _ => {}
+        }
+
+        let proposal_id = self.next_multisig_proposal_id;
+        self.next_multisig_proposal_id += 1;
+        self.multisig_proposals.insert(&proposal_id, &MultisigProposal {
+            id: proposal_id,
+            proposer: env::predecessor_account_id(),
+            kind,
+            confirmations: vec![],
+            expires_at: env::block_timestamp().checked_add(MULTISIG_PROPOSAL_DURATION).expect("ERR_INTEGER_OVERFLOW").into(),
+        });
+        log_multisig_event("multisig_proposal_added", proposal_id);
+
+        self.confirm_multisig_proposal(proposal_id);
+        proposal_id
+    }
+
+    // returns true if the confirmation executed the proposal
+    #[payable]
+    pub fn confirm_multisig_proposal(&mut self, proposal_id: u64) -> bool {
+        assert_one_yocto_or_contract_caller();
+        self.assert_multisig_signer();
+        let mut proposal = self.multisig_proposals.get(&proposal_id).expect("ERR_PROPOSAL_NOT_FOUND");
+        assert!(env::block_timestamp() < proposal.expires_at.0, "ERR_PROPOSAL_EXPIRED");
+        let predecessor_account_id = env::predecessor_account_id();
+        assert!(!proposal.confirmations.contains(&predecessor_account_id), "ERR_ALREADY_CONFIRMED");
+
+        proposal.confirmations.push(predecessor_account_id);
+        log_multisig_event("multisig_proposal_confirmed", proposal_id);
+        if proposal.confirmations.len() < self.multisig_threshold as usize {
+            self.multisig_proposals.insert(&proposal_id, &proposal);
+            return false;
+        }
+
+        self.multisig_proposals.remove(&proposal_id);
+        self.internal_execute_multisig_proposal(proposal.kind);
+        log_multisig_event("multisig_proposal_executed", proposal_id);
+        true
+    }
+
+    // the proposer can cancel its proposal, any signer can clean up an expired one
+    #[payable]
+    pub fn cancel_multisig_proposal(&mut self, proposal_id: u64) {
+        assert_one_yocto_or_contract_caller();
+        self.assert_multisig_signer();
+        let proposal = self.multisig_proposals.get(&proposal_id).expect("ERR_PROPOSAL_NOT_FOUND");
+        assert!(
+            proposal.proposer == env::predecessor_account_id() || proposal.expires_at.0 <= env::block_timestamp(),
+            "ERR_NOT_PROPOSER"
+        );
+
+        self.multisig_proposals.remove(&proposal_id);
+        log_multisig_event("multisig_proposal_cancelled", proposal_id);
+    }
+}
+
+impl Contract {
+    pub(crate) fn is_multisig_enabled(&self) -> bool {
+        self.multisig_threshold > 0
+    }
+
+    pub(crate) fn assert_multisig_disabled(&self) {
+        assert!(!self.is_multisig_enabled(), "ERR_MULTISIG_REQUIRED");
+    }
+
+    pub(crate) fn assert_multisig_signer(&self) {
+        assert!(self.multisig_signers.contains(&env::predecessor_account_id()), "ERR_NOT_MULTISIG_SIGNER");
+    }
+
+    fn internal_set_multisig(&mut self, signers: Vec<AccountId>, threshold: u8) {
+        assert_valid_multisig(&signers, threshold);
+        self.multisig_signers = signers;
+        self.multisig_threshold = threshold;
+        self.multisig_proposals.clear();
+    }
+
+    // the same checks as the direct calls, apart from who the caller is
+    fn internal_execute_multisig_proposal(&mut self, kind: MultisigProposalKind) {
+        match kind {
+            MultisigProposalKind::Revoke => {
+                self.internal_start_revoke();
+            }
+            MultisigProposalKind::ChangeRecipient { recipient } => {
+                self.assert_not_paused();
+                self.internal_set_recipient(recipient, None);
+            }
+            MultisigProposalKind::TransferOwnership { owner } => self.internal_transfer_ownership(owner),
+            MultisigProposalKind::AmendGrant { amendment } => self.internal_amend_grant(amendment),
+            MultisigProposalKind::SetMultisig { signers, threshold } => self.internal_set_multisig(signers, threshold),
+            MultisigProposalKind::StageUpgrade { code_hash } => self.internal_approve_upgrade(code_hash),
+            MultisigProposalKind::DeployUpgrade => {
+                self.internal_deploy_upgrade();
+            }
+            MultisigProposalKind::PauseVesting => self.internal_pause_vesting(),
+            MultisigProposalKind::ResumeVesting => self.internal_resume_vesting(),
+            MultisigProposalKind::ReclaimExpiredMilestone { milestone_id } => {
+                self.internal_reclaim_expired_milestone(milestone_id);
+            }
+            MultisigProposalKind::SetAccelerator { accelerator } => self.internal_set_accelerator(accelerator),
+        }
+    }
+}
+
+fn assert_valid_account_id(account_id: &AccountId) {
+    assert!(env::is_valid_account_id(account_id.as_bytes()), "ERR_INVALID_ACCOUNT_ID");
+}
+
+fn assert_valid_multisig(signers: &[AccountId], threshold: u8) {
+    assert!(signers.len() <= MAX_MULTISIG_SIGNERS, "ERR_TOO_MANY_SIGNERS");
+    assert!(threshold > 0 && threshold as usize <= signers.len(), "ERR_INVALID_THRESHOLD");
+    for (index, signer) in signers.iter().enumerate() {
+        assert_valid_account_id(signer);
+        assert!(!signers[..index].contains(signer), "ERR_DUPLICATE_SIGNER");
+    }
+}
+
+fn log_multisig_event(event: &str, proposal_id: u64) {
+    env::log(format!(
+        "EVENT_JSON:{}",
+        json!({
+            "standard": "paras-vesting",
+            "version": "1.0.0",
+            "event": event,
+            "data": [{
+                "proposal_id": U64(proposal_id),
+                "sender_id": env::predecessor_account_id(),
+            }],
+        })
+    ).as_bytes());
+}
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+
+use near_contract_standards::non_fungible_token::metadata::{NFTContractMetadata, TokenMetadata, NFT_METADATA_SPEC};
+use near_contract_standards::non_fungible_token::{Token, TokenId};
+use near_sdk::collections::LazyOption;
+use near_sdk::json_types::{U128, ValidAccountId};
+use near_sdk::serde_json::json;
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Promise, PromiseOrValue, PromiseResult};
+
+use crate::utils::{
+    ext_non_fungible_approval_receiver, ext_non_fungible_token_receiver, ext_self, GAS_FOR_NFT_ON_APPROVE, GAS_FOR_NFT_ON_TRANSFER,
+    GAS_FOR_NFT_RESOLVE_TRANSFER
+};
+use crate::{Contract, GrantTransferPolicy, StorageKey};
+
+// the contract holds a single grant, exposed as the only token of the collection (NEP-171/177/181)
+pub const GRANT_TOKEN_ID: &str = "0";
+
+#[near_bindgen]
+impl Contract {
+    pub fn nft_metadata(&self) -> NFTContractMetadata {
+        NFTContractMetadata {
+            spec: NFT_METADATA_SPEC.to_string(),
+            name: "Paras Vesting Grant".to_string(),
+            symbol: "PVG".to_string(),
+            icon: None,
+            base_uri: None,
+            reference: None,
+            reference_hash: None,
+        }
+    }
+
+    pub fn nft_token(&self, token_id: TokenId) -> Option<Token> {
+        if token_id == GRANT_TOKEN_ID {
+            Some(self.internal_grant_token())
+        } else {
+            None
+        }
+    }
+
+    pub fn nft_total_supply(&self) -> U128 {
+        U128(1)
+    }
+
+    pub fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        if from_index.map_or(0, |from_index| from_index.0) == 0 && limit != Some(0) {
+            vec![self.internal_grant_token()]
+        } else {
+            vec![]
+        }
+    }
+
+    pub fn nft_supply_for_owner(&self, account_id: ValidAccountId) -> U128 {
+        if account_id.as_ref() == &self.recipient { U128(1) } else { U128(0) }
+    }
+
+    pub fn nft_tokens_for_owner(&self, account_id: ValidAccountId, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        if account_id.as_ref() == &self.recipient {
+            self.nft_tokens(from_index, limit)
+        } else {
+            vec![]
+        }
+    }
+
+    // holding the token is being the recipient
+    #[payable]
+    pub fn nft_transfer(
+        &mut self,
+        receiver_id: ValidAccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        assert_one_yocto();
+        self.internal_nft_transfer(receiver_id.into(), token_id, approval_id, memo);
+    }
+
+    #[payable]
+    pub fn nft_transfer_call(
+        &mut self,
+        receiver_id: ValidAccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        assert_one_yocto();
+        let previous_owner_id = self.internal_nft_transfer(receiver_id.clone().into(), token_id.clone(), approval_id, memo);
+
+        ext_non_fungible_token_receiver::nft_on_transfer(
+            env::predecessor_account_id(),
+            previous_owner_id.clone(),
+            token_id.clone(),
+            msg,
+            receiver_id.as_ref(),
+            0,
+            GAS_FOR_NFT_ON_TRANSFER
+        ).then(ext_self::nft_resolve_transfer(
+            previous_owner_id,
+            receiver_id.into(),
+            token_id,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_NFT_RESOLVE_TRANSFER
+        )).into()
+    }
+
+    // returns true if the token was kept by the receiver, false if it went back to the previous owner
+    #[private]
+    pub fn nft_resolve_transfer(&mut self, previous_owner_id: AccountId, receiver_id: AccountId, token_id: TokenId) -> bool {
+        assert_eq!(token_id, GRANT_TOKEN_ID, "ERR_TOKEN_NOT_FOUND");
+        let must_revert = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<bool>(&value).unwrap_or(true)
+            }
+            _ => true,
+        };
+        if !must_revert {
+            return true;
+        }
+
+        // the receiver may have already passed the grant on
+        if self.recipient != receiver_id {
+            return true;
+        }
+
+        // approvals given by the previous owner were cleared by the transfer
+        self.internal_set_recipient(previous_owner_id, None);
+        false
+    }
+
+    // NEP-178, lets a marketplace transfer the grant on behalf of the recipient.
+    // The deposit pays for the storage of the approval, the rest is refunded.
+    #[payable]
+    pub fn nft_approve(&mut self, token_id: TokenId, account_id: ValidAccountId, msg: Option<String>) -> Option<Promise> {
+        assert_eq!(token_id, GRANT_TOKEN_ID, "ERR_TOKEN_NOT_FOUND");
+        assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
+        self.assert_nft_transferable();
+
+        let initial_storage_usage = env::storage_usage();
+        let approval_id = next_approval_id_storage().get().unwrap_or(0);
+        next_approval_id_storage().set(&(approval_id + 1));
+        let mut approvals = nft_approvals();
+        approvals.insert(account_id.clone().into(), approval_id);
+        set_nft_approvals(&approvals);
+
+        let storage_cost = (env::storage_usage() - initial_storage_usage) as u128 * env::storage_byte_cost();
+        assert!(env::attached_deposit() >= std::cmp::max(storage_cost, 1), "ERR_NOT_ENOUGH_DEPOSIT_FOR_STORAGE");
+        let refund = env::attached_deposit() - storage_cost;
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+
+        msg.map(|msg| {
+            ext_non_fungible_approval_receiver::nft_on_approve(
+                token_id,
+                self.recipient(),
+                approval_id,
+                msg,
+                account_id.as_ref(),
+                0,
+                GAS_FOR_NFT_ON_APPROVE
+            )
+        })
+    }
+
+    #[payable]
+    pub fn nft_revoke(&mut self, token_id: TokenId, account_id: ValidAccountId) {
+        assert_one_yocto();
+        assert_eq!(token_id, GRANT_TOKEN_ID, "ERR_TOKEN_NOT_FOUND");
+        assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
+
+        let initial_storage_usage = env::storage_usage();
+        let mut approvals = nft_approvals();
+        if approvals.remove(account_id.as_ref()).is_some() {
+            set_nft_approvals(&approvals);
+            refund_released_storage(&self.recipient, initial_storage_usage);
+        }
+    }
+
+    #[payable]
+    pub fn nft_revoke_all(&mut self, token_id: TokenId) {
+        assert_one_yocto();
+        assert_eq!(token_id, GRANT_TOKEN_ID, "ERR_TOKEN_NOT_FOUND");
+        assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
+
+        internal_clear_nft_approvals(&self.recipient);
+    }
+
+    pub fn nft_is_approved(&self, token_id: TokenId, approved_account_id: ValidAccountId, approval_id: Option<u64>) -> bool {
+        if token_id != GRANT_TOKEN_ID {
+            return false;
+        }
+        match nft_approvals().get(approved_account_id.as_ref()) {
+            Some(current_approval_id) => approval_id.map_or(true, |approval_id| approval_id == *current_approval_id),
+            None => false,
+        }
+    }
+}
+
+impl Contract {
+    fn internal_nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) -> AccountId {
+        assert_eq!(token_id, GRANT_TOKEN_ID, "ERR_TOKEN_NOT_FOUND");
+        let sender_id = env::predecessor_account_id();
+        if sender_id != self.recipient {
+            let current_approval_id = *nft_approvals().get(&sender_id).expect("ERR_SENDER_NOT_APPROVED");
+            assert!(
+                approval_id.map_or(true, |approval_id| approval_id == current_approval_id),
+                "ERR_APPROVAL_ID_MISMATCH"
+            );
+        }
+        self.assert_nft_transferable();
+        assert_ne!(receiver_id, self.recipient, "ERR_SAME_RECIPIENT");
+
+        let previous_owner_id = self.recipient();
+        self.internal_set_recipient(receiver_id, memo);
+        previous_owner_id
+    }
+
+    fn assert_nft_transferable(&self) {
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        self.assert_not_paused();
+        // a revocable grant stays with the recipient the owner granted it to
+        assert!(!self.revocable, "ERR_GRANT_IS_REVOCABLE");
+        assert_eq!(self.transfer_policy, GrantTransferPolicy::Allowed, "ERR_GRANT_TRANSFER_NOT_ALLOWED");
+    }
+
+    fn internal_grant_token(&self) -> Token {
+        let vesting_info = self.get_vesting_info();
+        Token {
+            token_id: GRANT_TOKEN_ID.to_string(),
+            owner_id: self.recipient(),
+            metadata: Some(TokenMetadata {
+                title: Some(format!("Vesting grant {}", env::current_account_id())),
+                description: Some(format!(
+                    "{} of {} vesting from {} over {} (nanoseconds), {} claimed",
+                    vesting_info.amount.0,
+                    vesting_info.token.clone().unwrap_or_else(|| "NEAR".to_string()),
+                    vesting_info.start.0,
+                    vesting_info.duration.0,
+                    vesting_info.amount_claimed.0
+                )),
+                media: None,
+                media_hash: None,
+                copies: Some(1),
+                issued_at: None,
+                expires_at: None,
+                starts_at: None,
+                updated_at: None,
+                // full schedule for wallets and marketplaces
+                extra: Some(near_sdk::serde_json::to_string(&vesting_info).expect("ERR_SERIALIZE")),
+                reference: None,
+                reference_hash: None,
+            }),
+            approved_account_ids: Some(nft_approvals()),
+        }
+    }
+}
+
+// approvals are given by the current recipient, every change of recipient drops them
+// and refunds their storage to the recipient that paid for it
+pub(crate) fn internal_clear_nft_approvals(recipient: &AccountId) {
+    let initial_storage_usage = env::storage_usage();
+    if nft_approvals_storage().remove() {
+        refund_released_storage(recipient, initial_storage_usage);
+    }
+}
+
+fn refund_released_storage(account_id: &AccountId, initial_storage_usage: u64) {
+    let refund = (initial_storage_usage - env::storage_usage()) as u128 * env::storage_byte_cost();
+    if refund > 0 {
+        Promise::new(account_id.clone()).transfer(refund);
+    }
+}
+
+// kept out of the Contract struct, so the state layout doesn't change
+fn nft_approvals() -> HashMap<AccountId, u64> {
+    nft_approvals_storage().get().unwrap_or_default()
+}
+
+fn set_nft_approvals(approvals: &HashMap<AccountId, u64>) {
+    if approvals.is_empty() {
+        nft_approvals_storage().remove();
+    } else {
+        nft_approvals_storage().set(approvals);
+    }
+}
+
+fn nft_approvals_storage() -> LazyOption<HashMap<AccountId, u64>> {
+    LazyOption::new(StorageKey::NftApprovals, None)
+}
+
+fn next_approval_id_storage() -> LazyOption<u64> {
+    LazyOption::new(StorageKey::NftNextApprovalId, None)
+}
+
+// NEP-297 event logs so indexers pick up the token
+pub(crate) fn log_nft_mint(owner_id: &AccountId) {
+    log_nft_event("nft_mint", json!([{
+        "owner_id": owner_id,
+        "token_ids": [GRANT_TOKEN_ID],
+    }]));
+}
+
+pub(crate) fn log_nft_burn(owner_id: &AccountId) {
+    log_nft_event("nft_burn", json!([{
+        "owner_id": owner_id,
+        "token_ids": [GRANT_TOKEN_ID],
+    }]));
+}
+
+pub(crate) fn log_nft_transfer(old_owner_id: &AccountId, new_owner_id: &AccountId, memo: Option<String>) {
+    log_nft_event("nft_transfer", json!([{
+        "old_owner_id": old_owner_id,
+        "new_owner_id": new_owner_id,
+        "token_ids": [GRANT_TOKEN_ID],
+        "memo": memo,
+    }]));
+}
+
+fn log_nft_event(event: &str, data: near_sdk::serde_json::Value) {
+    env::log(format!(
+        "EVENT_JSON:{}",
+        json!({
+            "standard": "nep171",
+            "version": "1.0.0",
+            "event": event,
+            "data": data,
+        })
+    ).as_bytes());
+}
@@ -0,0 +1,113 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, near_bindgen, AccountId, Promise};
+
+use crate::utils::{assert_one_yocto_or_contract_caller, ext_self, is_promise_success, GAS_FOR_RESOLVE_PUSH};
+use crate::Contract;
+
+#[near_bindgen]
+impl Contract {
+    // keepers push vested tokens to the recipient, at most once every min_interval
+    // and reimbursed with keeper_reward (in yoctoNEAR) from the keeper fund
+    #[payable]
+    pub fn set_push_config(&mut self, min_interval: U64, keeper_reward: U128) {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+
+        self.push_min_interval = min_interval.0;
+        self.keeper_reward = keeper_reward.0;
+    }
+
+    #[payable]
+    pub fn deposit_keeper_fund(&mut self) -> U128 {
+        assert!(env::attached_deposit() > 0, "ERR_DEPOSIT_IS_ZERO");
+
+        self.keeper_fund = self.keeper_fund.checked_add(env::attached_deposit()).expect("ERR_INTEGER_OVERFLOW");
+        self.keeper_fund.into()
+    }
+
+    #[payable]
+    pub fn withdraw_keeper_fund(&mut self, amount: U128) -> Promise {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+        assert!(amount.0 <= self.keeper_fund, "ERR_AMOUNT_EXCEEDS_KEEPER_FUND");
+
+        self.keeper_fund -= amount.0;
+        Promise::new(self.owner()).transfer(amount.0)
+    }
+
+    // pushes release the whole releasable amount, so the recipient has to opt in
+    #[payable]
+    pub fn set_push_enabled(&mut self, enabled: bool) {
+        assert_one_yocto_or_contract_caller();
+        assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
+
+        self.push_enabled = enabled;
+    }
+
+    pub fn push_enabled(&self) -> bool {
+        self.push_enabled
+    }
+
+    pub fn push_min_interval(&self) -> U64 {
+        self.push_min_interval.into()
+    }
+
+    pub fn last_pushed_at(&self) -> U64 {
+        self.last_pushed_at.into()
+    }
+
+    pub fn keeper_reward(&self) -> U128 {
+        self.keeper_reward.into()
+    }
+
+    pub fn keeper_fund(&self) -> U128 {
+        self.keeper_fund.into()
+    }
+
+    // callable by anyone, tokens always go to the recipient
+    pub fn push_vested(&mut self) -> Promise {
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        self.assert_not_paused();
+        assert!(self.push_enabled, "ERR_PUSH_NOT_ENABLED");
+        assert!(
+            env::block_timestamp() >= self.last_pushed_at.checked_add(self.push_min_interval).expect("ERR_INTEGER_OVERFLOW"),
+            "ERR_PUSH_TOO_EARLY"
+        );
+        // staked tokens stay where they are
+        let releasable = std::cmp::min(self.internal_releasable_amount(), self.internal_liquid_balance());
+        assert!(releasable > 0, "ERR_NO_VESTED_AMOUNT_ARE_DUE");
+
+        self.last_pushed_at = env::block_timestamp();
+        self.amount_claimed = self.amount_claimed.checked_add(releasable).expect("ERR_INTEGER_OVERFLOW");
+
+        // reserved now, paid once the transfer succeeded
+        let keeper_reward = if self.keeper_fund >= self.keeper_reward { self.keeper_reward } else { 0 };
+        self.keeper_fund -= keeper_reward;
+        self.internal_transfer_extra_tokens(&self.recipient());
+        self.internal_checkpoint();
+
+        self.internal_transfer_asset(self.recipient(), releasable).then(ext_self::callback_push_vested(
+            env::predecessor_account_id(),
+            releasable.into(),
+            keeper_reward.into(),
+            &env::current_account_id(),
+            0,
+            GAS_FOR_RESOLVE_PUSH
+        ))
+    }
+
+    #[private]
+    pub fn callback_push_vested(&mut self, keeper_id: AccountId, amount: U128, keeper_reward: U128) -> U128 {
+        if is_promise_success() {
+            if keeper_reward.0 > 0 {
+                Promise::new(keeper_id).transfer(keeper_reward.0);
+            }
+            amount
+        } else {
+            self.amount_claimed = self.amount_claimed.checked_sub(amount.0).expect("ERR_INTEGER_OVERFLOW");
+            self.keeper_fund = self.keeper_fund.checked_add(keeper_reward.0).expect("ERR_INTEGER_OVERFLOW");
+            self.internal_checkpoint();
+            U128(0)
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::ValidAccountId;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::utils::assert_one_yocto_or_contract_caller;
+use crate::Contract;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    // grants and revokes roles
+    Admin,
+    Revoker,
+    RecipientManager,
+    Pauser,
+}
+
+const ALL_ROLES: [Role; 4] = [Role::Admin, Role::Revoker, Role::RecipientManager, Role::Pauser];
+
+// one bit per role
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Default)]
+pub struct RoleSet(u8);
+
+impl RoleSet {
+    fn bit(role: Role) -> u8 {
+        1 << (role as u8)
+    }
+
+    pub fn contains(&self, role: Role) -> bool {
+        self.0 & Self::bit(role) != 0
+    }
+
+    pub fn insert(&mut self, role: Role) -> bool {
+        let inserted = !self.contains(role);
+        self.0 |= Self::bit(role);
+        inserted
+    }
+
+    pub fn remove(&mut self, role: Role) -> bool {
+        let removed = self.contains(role);
+        self.0 &= !Self::bit(role);
+        removed
+    }
+
+    pub fn to_vec(&self) -> Vec<Role> {
+        ALL_ROLES.iter().copied().filter(|role| self.contains(*role)).collect()
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn has_role(&self, account_id: ValidAccountId, role: Role) -> bool {
+        self.internal_has_role(account_id.as_ref(), role)
+    }
+
+    pub fn roles_of(&self, account_id: ValidAccountId) -> Vec<Role> {
+        self.roles.get(account_id.as_ref()).unwrap_or_default().to_vec()
+    }
+
+    #[payable]
+    pub fn grant_role(&mut self, account_id: ValidAccountId, role: Role) {
+        assert_one_yocto_or_contract_caller();
+        self.assert_role(Role::Admin);
+
+        assert!(self.internal_grant_role(account_id.as_ref(), role), "ERR_ROLE_ALREADY_GRANTED");
+    }
+
+    #[payable]
+    pub fn revoke_role(&mut self, account_id: ValidAccountId, role: Role) {
+        assert_one_yocto_or_contract_caller();
+        self.assert_role(Role::Admin);
+
+        assert!(self.internal_revoke_role(account_id.as_ref(), role), "ERR_ROLE_NOT_GRANTED");
+    }
+
+    // e.g. a hot key giving up a role it doesn't need anymore
+    #[payable]
+    pub fn renounce_role(&mut self, role: Role) {
+        assert_one_yocto_or_contract_caller();
+
+        assert!(self.internal_revoke_role(&env::predecessor_account_id(), role), "ERR_ROLE_NOT_GRANTED");
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_has_role(&self, account_id: &AccountId, role: Role) -> bool {
+        self.roles.get(account_id).map_or(false, |roles| roles.contains(role))
+    }
+
+    pub(crate) fn assert_role(&self, role: Role) {
+        assert!(self.internal_has_role(&env::predecessor_account_id(), role), "ERR_MISSING_ROLE");
+    }
+
+    pub(crate) fn internal_grant_role(&mut self, account_id: &AccountId, role: Role) -> bool {
+        let mut roles = self.roles.get(account_id).unwrap_or_default();
+        if !roles.insert(role) {
+            return false;
+        }
+        self.roles.insert(account_id, &roles);
+        log_role_event("role_granted", account_id, role);
+        true
+    }
+
+    pub(crate) fn internal_revoke_role(&mut self, account_id: &AccountId, role: Role) -> bool {
+        let mut roles = match self.roles.get(account_id) {
+            Some(roles) => roles,
+            None => return false,
+        };
+        if !roles.remove(role) {
+            return false;
+        }
+        if roles.0 == 0 {
+            self.roles.remove(account_id);
+        } else {
+            self.roles.insert(account_id, &roles);
+        }
+        log_role_event("role_revoked", account_id, role);
+        true
+    }
+}
+
+fn log_role_event(event: &str, account_id: &AccountId, role: Role) {
+    env::log(format!(
+        "EVENT_JSON:{}",
+        json!({
+            "standard": "paras-vesting",
+            "version": "1.0.0",
+            "event": event,
+            "data": [{
+                "account_id": account_id,
+                "role": role,
+                "sender_id": env::predecessor_account_id(),
+            }],
+        })
+    ).as_bytes());
+}
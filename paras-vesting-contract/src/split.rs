@@ -0,0 +1,333 @@
+use near_sdk::collections::LazyOption;
+use near_sdk::json_types::{Base58CryptoHash, Base64VecU8, U128, U64, ValidAccountId};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, AccountId, CryptoHash, Promise, PromiseOrValue};
+
+use crate::nft::{log_nft_burn, log_nft_mint};
+use crate::utils::{
+    assert_one_yocto_or_contract_caller, ext_fungible_token, ext_self, ext_storage_management, is_promise_success, mul_div,
+    GAS_FOR_FT_TRANSFER, GAS_FOR_NEW_SPLIT_GRANT, GAS_FOR_RESOLVE_FUND_SPLIT, GAS_FOR_RESOLVE_SPLIT, GAS_FOR_STORAGE_DEPOSIT,
+    NATIVE_STORAGE_RESERVE, SPLIT_GRANT_STORAGE_DEPOSIT, STORAGE_DEPOSIT_FOR_FT,
+};
+use crate::{Contract, GrantTransferPolicy, GrantType, PausedInterval, StorageKey, VestingAsset, VestingCurve};
+
+// deploying the code to every child takes most of the gas of a split
+pub const MAX_SPLIT_GRANTS: usize = 2;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GrantShare {
+    pub recipient: ValidAccountId,
+    pub amount: U128,
+}
+
+// init args of a child grant, everything the child takes over from the parent
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SplitGrant {
+    pub owner: AccountId,
+    pub recipient: AccountId,
+    pub asset: VestingAsset,
+    pub amount: U128,
+    pub amount_claimed: U128,
+    pub amount_accelerated: U128,
+    pub start: U64,
+    pub duration: U64,
+    pub cliff: U64,
+    pub curve: VestingCurve,
+    pub paused_intervals: Vec<PausedInterval>,
+    pub revocable: bool,
+    pub transfer_policy: GrantTransferPolicy,
+}
+
+// Each share becomes a child grant on its own sub-account, deployed with code whose hash
+// the owner approved. Children keep the owner, the revocable flag and the schedule, owner
+// settings like the guardian, accelerator or staking pools start fresh.
+#[near_bindgen]
+impl Contract {
+    #[init]
+    pub fn new_split_grant(grant: SplitGrant) -> Self {
+        // only created by the parent, in the same batch that created the sub-account
+        assert!(
+            env::current_account_id().ends_with(&format!(".{}", env::predecessor_account_id())),
+            "ERR_NOT_CREATED_BY_PARENT_GRANT"
+        );
+        assert!(grant.amount_claimed.0 <= grant.amount.0, "ERR_INVALID_SPLIT_GRANT");
+        assert!(grant.amount_accelerated.0 <= grant.amount.0, "ERR_INVALID_SPLIT_GRANT");
+
+        let mut this = Self::internal_new(
+            grant.owner,
+            grant.recipient,
+            grant.asset,
+            grant.amount.0,
+            grant.start.0,
+            grant.duration.0,
+            grant.cliff.0,
+            grant.revocable,
+            grant.transfer_policy,
+            GrantType::Time,
+        );
+        this.amount_claimed = grant.amount_claimed.0;
+        this.amount_accelerated = grant.amount_accelerated.0;
+        this.curve = grant.curve;
+        this.paused_intervals = grant.paused_intervals;
+        this.internal_checkpoint();
+
+        if this.asset == VestingAsset::Native {
+            let storage_cost = env::storage_usage() as u128 * env::storage_byte_cost();
+            assert!(
+                env::account_balance().saturating_sub(this.amount - this.amount_claimed) >= storage_cost + NATIVE_STORAGE_RESERVE,
+                "ERR_NOT_ENOUGH_BALANCE_FOR_STORAGE"
+            );
+        }
+        this
+    }
+
+    // None disables splits
+    #[payable]
+    pub fn set_split_code_hash(&mut self, code_hash: Option<Base58CryptoHash>) {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+        self.assert_multisig_disabled();
+
+        match code_hash {
+            Some(code_hash) => split_code_hash_storage().set(&code_hash),
+            None => split_code_hash_storage().remove(),
+        };
+    }
+
+    pub fn split_code_hash(&self) -> Option<Base58CryptoHash> {
+        split_code_hash_storage().get()
+    }
+
+    // The attached deposit pays for the children's accounts, split evenly between them.
+    // A child that fails to be created or funded goes back into this grant, still vesting
+    // to the current recipient.
+    #[payable]
+    pub fn split_grant(&mut self, shares: Vec<GrantShare>, code: Base64VecU8) -> Vec<AccountId> {
+        assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        self.assert_not_paused();
+        // the children go to other recipients, so a split is a grant transfer
+        assert_eq!(self.transfer_policy, GrantTransferPolicy::Allowed, "ERR_GRANT_TRANSFER_NOT_ALLOWED");
+        assert!(self.grant_type == GrantType::Time, "ERR_SPLIT_NOT_SUPPORTED_FOR_MILESTONES");
+        assert!(self.extra_tokens.is_empty(), "ERR_SPLIT_NOT_SUPPORTED_FOR_MULTI_TOKEN");
+        assert_eq!(self.staked_principal, 0, "ERR_TOKENS_STAKED");
+        assert!(self.pending_amendment.is_none(), "ERR_PENDING_AMENDMENT");
+
+        let code: Vec<u8> = code.into();
+        let approved_code_hash: CryptoHash = split_code_hash_storage().get().expect("ERR_NO_SPLIT_CODE_HASH").into();
+        assert_eq!(env::sha256(&code), approved_code_hash.to_vec(), "ERR_CODE_HASH_NOT_APPROVED");
+
+        assert!(shares.len() >= 2 && shares.len() <= MAX_SPLIT_GRANTS, "ERR_INVALID_NUMBER_OF_SHARES");
+        let total = shares.iter().fold(0u128, |total, share| {
+            assert!(share.amount.0 > 0, "ERR_SHARE_IS_ZERO");
+            total.checked_add(share.amount.0).expect("ERR_INTEGER_OVERFLOW")
+        });
+        assert_eq!(total, self.amount, "ERR_SHARES_DONT_COVER_GRANT");
+
+        let deposit = env::attached_deposit() / shares.len() as u128;
+        assert!(
+            deposit >= code.len() as u128 * env::storage_byte_cost() + SPLIT_GRANT_STORAGE_DEPOSIT,
+            "ERR_NOT_ENOUGH_DEPOSIT_FOR_STORAGE"
+        );
+
+        let grants = self.internal_split_amounts(&shares);
+        let children: Vec<AccountId> = (0..grants.len())
+            .map(|index| format!("split-{}-{}.{}", env::block_index(), index, env::current_account_id()))
+            .collect();
+        assert!(
+            children.iter().all(|child| env::is_valid_account_id(child.as_bytes())),
+            "ERR_INVALID_CHILD_ACCOUNT_ID"
+        );
+
+        // everything moves to the children, restored below if one of them fails
+        log_nft_burn(&self.recipient);
+        log_grant_split(&self.recipient, &children, &grants);
+        self.is_active = false;
+        self.amount = 0;
+        self.amount_claimed = 0;
+        self.amount_accelerated = 0;
+        self.pending_grant_transfer = None;
+        self.internal_checkpoint();
+        set_pending_splits(internal_pending_splits() + grants.len() as u64);
+
+        for (child, grant) in children.iter().zip(grants.into_iter()) {
+            let amount_not_claimed = grant.amount.0 - grant.amount_claimed.0;
+            let transfer = match grant.asset {
+                VestingAsset::FungibleToken(_) => deposit - STORAGE_DEPOSIT_FOR_FT,
+                VestingAsset::Native => deposit + amount_not_claimed,
+            };
+            Promise::new(child.clone())
+                .create_account()
+                .transfer(transfer)
+                .deploy_contract(code.clone())
+                .function_call(
+                    b"new_split_grant".to_vec(),
+                    json!({ "grant": grant }).to_string().into_bytes(),
+                    0,
+                    GAS_FOR_NEW_SPLIT_GRANT,
+                )
+                .then(ext_self::callback_split_grant(
+                    child.clone(),
+                    grant,
+                    deposit.into(),
+                    &env::current_account_id(),
+                    0,
+                    GAS_FOR_RESOLVE_SPLIT,
+                ));
+        }
+        children
+    }
+
+    // funds a created fungible token child, or takes back the share of a child that failed
+    #[private]
+    pub fn callback_split_grant(&mut self, account_id: AccountId, grant: SplitGrant, deposit: U128) -> PromiseOrValue<bool> {
+        if !is_promise_success() {
+            // the batch is rolled back, the native amount and the deposit came back here
+            self.internal_restore_split(&grant);
+            Promise::new(self.recipient()).transfer(deposit.0);
+            return PromiseOrValue::Value(false);
+        }
+
+        match &grant.asset {
+            VestingAsset::FungibleToken(token) => {
+                let amount_not_claimed = grant.amount.0 - grant.amount_claimed.0;
+                ext_storage_management::storage_deposit(
+                    Some(account_id.clone()),
+                    Some(true),
+                    token,
+                    STORAGE_DEPOSIT_FOR_FT,
+                    GAS_FOR_STORAGE_DEPOSIT,
+                )
+                .then(ext_fungible_token::ft_transfer(
+                    account_id.clone(),
+                    amount_not_claimed.into(),
+                    None,
+                    token,
+                    1,
+                    GAS_FOR_FT_TRANSFER,
+                ))
+                .then(ext_self::callback_fund_split_grant(
+                    account_id,
+                    grant,
+                    &env::current_account_id(),
+                    0,
+                    GAS_FOR_RESOLVE_FUND_SPLIT,
+                ))
+                .into()
+            }
+            VestingAsset::Native => {
+                set_pending_splits(internal_pending_splits() - 1);
+                PromiseOrValue::Value(true)
+            }
+        }
+    }
+
+    // an unfunded child holds nothing, its share keeps vesting here
+    #[private]
+    pub fn callback_fund_split_grant(&mut self, account_id: AccountId, grant: SplitGrant) -> bool {
+        if !is_promise_success() {
+            env::log(format!("Child grant {} could not be funded", account_id).as_bytes());
+            self.internal_restore_split(&grant);
+            return false;
+        }
+        set_pending_splits(internal_pending_splits() - 1);
+        true
+    }
+
+    // children still being created, the grant can't be finalized until they are settled
+    pub fn pending_splits(&self) -> u64 {
+        internal_pending_splits()
+    }
+}
+
+impl Contract {
+    // children get amount_claimed and amount_accelerated pro rata to their share,
+    // the last one takes the rounding so the children exactly cover the parent
+    pub(crate) fn internal_split_amounts(&self, shares: &[GrantShare]) -> Vec<SplitGrant> {
+        let mut amount_claimed_left = self.amount_claimed;
+        let mut amount_accelerated_left = self.amount_accelerated;
+        shares
+            .iter()
+            .enumerate()
+            .map(|(index, share)| {
+                let (amount_claimed, amount_accelerated) = if index + 1 == shares.len() {
+                    (amount_claimed_left, amount_accelerated_left)
+                } else {
+                    (
+                        mul_div(self.amount_claimed, share.amount.0, self.amount),
+                        mul_div(self.amount_accelerated, share.amount.0, self.amount),
+                    )
+                };
+                amount_claimed_left -= amount_claimed;
+                amount_accelerated_left -= amount_accelerated;
+                assert!(amount_claimed <= share.amount.0, "ERR_SHARE_BELOW_AMOUNT_CLAIMED");
+
+                SplitGrant {
+                    owner: self.owner(),
+                    recipient: share.recipient.clone().into(),
+                    asset: self.asset.clone(),
+                    amount: share.amount,
+                    amount_claimed: amount_claimed.into(),
+                    amount_accelerated: amount_accelerated.into(),
+                    start: self.start.into(),
+                    duration: self.duration.into(),
+                    cliff: self.cliff.into(),
+                    curve: self.curve.clone(),
+                    paused_intervals: self.paused_intervals.clone(),
+                    revocable: self.revocable,
+                    transfer_policy: self.transfer_policy.clone(),
+                }
+            })
+            .collect()
+    }
+
+    fn internal_restore_split(&mut self, grant: &SplitGrant) {
+        if !self.is_active {
+            self.is_active = true;
+            log_nft_mint(&self.recipient);
+        }
+        self.amount = self.amount.checked_add(grant.amount.0).expect("ERR_INTEGER_OVERFLOW");
+        self.amount_claimed = self.amount_claimed.checked_add(grant.amount_claimed.0).expect("ERR_INTEGER_OVERFLOW");
+        self.amount_accelerated = self.amount_accelerated.checked_add(grant.amount_accelerated.0).expect("ERR_INTEGER_OVERFLOW");
+        set_pending_splits(internal_pending_splits() - 1);
+        self.internal_checkpoint();
+    }
+}
+
+fn log_grant_split(recipient: &AccountId, children: &[AccountId], grants: &[SplitGrant]) {
+    env::log(format!(
+        "EVENT_JSON:{}",
+        json!({
+            "standard": "paras-vesting",
+            "version": "1.0.0",
+            "event": "grant_split",
+            "data": children.iter().zip(grants.iter()).map(|(child, grant)| json!({
+                "recipient": recipient,
+                "child": child,
+                "child_recipient": grant.recipient,
+                "amount": grant.amount,
+                "amount_claimed": grant.amount_claimed,
+            })).collect::<Vec<_>>(),
+        })
+    ).as_bytes());
+}
+
+// kept out of the Contract struct, so the state layout doesn't change
+fn split_code_hash_storage() -> LazyOption<Base58CryptoHash> {
+    LazyOption::new(StorageKey::SplitCodeHash, None)
+}
+
+pub(crate) fn internal_pending_splits() -> u64 {
+    pending_splits_storage().get().unwrap_or(0)
+}
+
+fn set_pending_splits(pending_splits: u64) {
+    pending_splits_storage().set(&pending_splits);
+}
+
+fn pending_splits_storage() -> LazyOption<u64> {
+    LazyOption::new(StorageKey::PendingSplits, None)
+}
@@ -0,0 +1,279 @@
+use near_sdk::json_types::{U128, ValidAccountId};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Promise, PromiseResult};
+
+use crate::utils::{
+    assert_one_yocto_or_contract_caller, ext_fungible_token, ext_self, ext_staking_pool, ext_token_staking, is_promise_success,
+    GAS_FOR_CLAIM_REWARD, GAS_FOR_DEPOSIT_AND_STAKE, GAS_FOR_FT_TRANSFER_CALL, GAS_FOR_RESOLVE_STAKING,
+    GAS_FOR_UNSTAKE, GAS_FOR_WITHDRAW
+};
+use crate::{Contract, VestingAsset};
+
+// Unvested tokens can be staked with one whitelisted pool at a time.
+// Staked principal stays subject to the schedule, rewards go straight to the recipient.
+// Native grants delegate to validator staking pools like the core lockup contract,
+// the principal counts as staked until it is withdrawn from the pool.
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn add_staking_pool(&mut self, pool_id: ValidAccountId) {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+
+        assert!(self.staking_pools.insert(pool_id.as_ref()), "ERR_STAKING_POOL_ALREADY_WHITELISTED");
+    }
+
+    // already staked principal can still be unstaked from a removed pool
+    #[payable]
+    pub fn remove_staking_pool(&mut self, pool_id: ValidAccountId) {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+
+        assert!(self.staking_pools.remove(pool_id.as_ref()), "ERR_STAKING_POOL_NOT_WHITELISTED");
+    }
+
+    pub fn staking_pools(&self) -> Vec<AccountId> {
+        self.staking_pools.to_vec()
+    }
+
+    pub fn staking_pool(&self) -> Option<AccountId> {
+        self.staking_pool.clone()
+    }
+
+    pub fn staked_principal(&self) -> U128 {
+        self.staked_principal.into()
+    }
+
+    // tokens of the grant held by the contract itself
+    pub fn liquid_balance(&self) -> U128 {
+        self.internal_liquid_balance().into()
+    }
+
+    #[payable]
+    pub fn stake_unvested(&mut self, pool_id: ValidAccountId, amount: U128) -> Promise {
+        assert_one_yocto();
+        assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        self.assert_not_paused();
+        let token = match &self.asset {
+            VestingAsset::FungibleToken(token) => token.clone(),
+            VestingAsset::Native => env::panic(b"ERR_NOT_SUPPORTED_FOR_NATIVE_GRANT"),
+        };
+        assert!(self.staking_pools.contains(pool_id.as_ref()), "ERR_STAKING_POOL_NOT_WHITELISTED");
+        assert!(
+            self.staking_pool.as_ref().map_or(true, |staking_pool| staking_pool == pool_id.as_ref()),
+            "ERR_ALREADY_STAKING_WITH_ANOTHER_POOL"
+        );
+        assert!(amount.0 > 0, "ERR_AMOUNT_IS_ZERO");
+        let amount_not_vested = self.amount.checked_sub(self.internal_calculate_amount_vested()).expect("ERR_INTEGER_OVERFLOW");
+        assert!(
+            self.staked_principal.checked_add(amount.0).expect("ERR_INTEGER_OVERFLOW") <= amount_not_vested,
+            "ERR_AMOUNT_EXCEEDS_UNVESTED_AMOUNT"
+        );
+        assert!(amount.0 <= self.internal_liquid_balance(), "ERR_AMOUNT_EXCEEDS_LIQUID_BALANCE");
+
+        self.staking_pool = Some(pool_id.clone().into());
+        self.staked_principal += amount.0;
+
+        ext_fungible_token::ft_transfer_call(
+            pool_id.into(),
+            amount,
+            None,
+            "".to_string(),
+            &token,
+            1,
+            GAS_FOR_FT_TRANSFER_CALL
+        ).then(ext_self::callback_stake(
+            amount,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_RESOLVE_STAKING
+        ))
+    }
+
+    // whatever the pool refunded is liquid again
+    #[private]
+    pub fn callback_stake(&mut self, amount: U128) -> U128 {
+        assert_eq!(env::promise_results_count(), 1, "ERR_TOO_MANY_RESULTS");
+        let amount_staked = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                match near_sdk::serde_json::from_slice::<U128>(&value) {
+                    Ok(amount_staked) => std::cmp::min(amount.0, amount_staked.0),
+                    Err(_) => amount.0,
+                }
+            }
+            _ => 0,
+        };
+
+        self.internal_on_unstaked(amount.0 - amount_staked);
+        amount_staked.into()
+    }
+
+    #[payable]
+    pub fn unstake(&mut self, pool_id: ValidAccountId, amount: U128) -> Promise {
+        // the owner of a revocable grant can be a DAO
+        assert_one_yocto_or_contract_caller();
+        self.assert_native_staking_caller();
+        self.assert_not_paused();
+        assert_eq!(self.staking_pool.as_ref(), Some(pool_id.as_ref()), "ERR_NOT_STAKING_WITH_POOL");
+        assert!(amount.0 > 0, "ERR_AMOUNT_IS_ZERO");
+
+        if self.asset == VestingAsset::Native {
+            // stays at the pool until withdraw_from_staking_pool
+            return self.internal_unstake(amount.0);
+        }
+        assert!(amount.0 <= self.staked_principal, "ERR_AMOUNT_EXCEEDS_STAKED_PRINCIPAL");
+
+        self.internal_unstake(amount.0).then(ext_self::callback_unstake(
+            amount,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_RESOLVE_STAKING
+        ))
+    }
+
+    // principal only counts as liquid once it is back
+    #[private]
+    pub fn callback_unstake(&mut self, amount: U128) -> U128 {
+        if is_promise_success() {
+            self.internal_on_unstaked(amount.0);
+            amount
+        } else {
+            U128(0)
+        }
+    }
+
+    #[payable]
+    pub fn deposit_and_stake(&mut self, pool_id: ValidAccountId, amount: U128) -> Promise {
+        assert_one_yocto();
+        assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
+        assert!(self.is_active, "ERR_VESTING_CONTRACT_NOT_ACTIVE");
+        self.assert_not_paused();
+        assert_eq!(self.asset, VestingAsset::Native, "ERR_ONLY_SUPPORTED_FOR_NATIVE_GRANT");
+        assert!(self.staking_pools.contains(pool_id.as_ref()), "ERR_STAKING_POOL_NOT_WHITELISTED");
+        assert!(
+            self.staking_pool.as_ref().map_or(true, |staking_pool| staking_pool == pool_id.as_ref()),
+            "ERR_ALREADY_STAKING_WITH_ANOTHER_POOL"
+        );
+        assert!(amount.0 > 0, "ERR_AMOUNT_IS_ZERO");
+        // vested but unclaimed NEAR can be staked too, as in the lockup contract
+        assert!(amount.0 <= self.internal_liquid_balance(), "ERR_AMOUNT_EXCEEDS_LIQUID_BALANCE");
+
+        self.staking_pool = Some(pool_id.clone().into());
+        self.staked_principal += amount.0;
+
+        ext_staking_pool::deposit_and_stake(
+            pool_id.as_ref(),
+            amount.0,
+            GAS_FOR_DEPOSIT_AND_STAKE
+        ).then(ext_self::callback_deposit_and_stake(
+            amount,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_RESOLVE_STAKING
+        ))
+    }
+
+    // a failed deposit is refunded to the contract
+    #[private]
+    pub fn callback_deposit_and_stake(&mut self, amount: U128) -> U128 {
+        if is_promise_success() {
+            amount
+        } else {
+            self.internal_on_unstaked(amount.0);
+            U128(0)
+        }
+    }
+
+    // unstaked NEAR can be withdrawn from the pool after the unbonding period
+    #[payable]
+    pub fn withdraw_from_staking_pool(&mut self, pool_id: ValidAccountId, amount: U128) -> Promise {
+        // the owner of a revocable grant can be a DAO
+        assert_one_yocto_or_contract_caller();
+        self.assert_native_staking_caller();
+        self.assert_not_paused();
+        assert_eq!(self.asset, VestingAsset::Native, "ERR_ONLY_SUPPORTED_FOR_NATIVE_GRANT");
+        assert_eq!(self.staking_pool.as_ref(), Some(pool_id.as_ref()), "ERR_NOT_STAKING_WITH_POOL");
+        assert!(amount.0 > 0, "ERR_AMOUNT_IS_ZERO");
+
+        ext_staking_pool::withdraw(
+            amount,
+            pool_id.as_ref(),
+            0,
+            GAS_FOR_WITHDRAW
+        ).then(ext_self::callback_withdraw_from_staking_pool(
+            amount,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_RESOLVE_STAKING
+        ))
+    }
+
+    // anything withdrawn above the principal is rewards and goes to the recipient
+    #[private]
+    pub fn callback_withdraw_from_staking_pool(&mut self, amount: U128) -> U128 {
+        if !is_promise_success() {
+            return U128(0);
+        }
+        let principal = std::cmp::min(amount.0, self.staked_principal);
+        self.internal_on_unstaked(principal);
+
+        let rewards = amount.0 - principal;
+        if rewards > 0 {
+            Promise::new(self.recipient()).transfer(rewards);
+        }
+        amount
+    }
+
+    // pool sends the rewards straight to the recipient
+    #[payable]
+    pub fn claim_staking_rewards(&mut self, pool_id: ValidAccountId) -> Promise {
+        assert_one_yocto();
+        assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
+        self.assert_not_paused();
+        assert!(
+            self.staking_pools.contains(pool_id.as_ref()) || self.staking_pool.as_ref() == Some(pool_id.as_ref()),
+            "ERR_STAKING_POOL_NOT_WHITELISTED"
+        );
+
+        ext_token_staking::claim_reward(
+            self.recipient(),
+            pool_id.as_ref(),
+            0,
+            GAS_FOR_CLAIM_REWARD
+        )
+    }
+}
+
+impl Contract {
+    // the owner of a revocable native grant can also bring the NEAR back from the pool before a revoke
+    fn assert_native_staking_caller(&self) {
+        let predecessor_account_id = env::predecessor_account_id();
+        assert!(
+            predecessor_account_id == self.recipient()
+                || (self.asset == VestingAsset::Native && self.revocable && predecessor_account_id == self.owner()),
+            "ERR_CALLER_NOT_RECIPIENT"
+        );
+    }
+
+    pub(crate) fn internal_liquid_balance(&self) -> u128 {
+        self.amount
+            .saturating_sub(self.amount_claimed)
+            .saturating_sub(self.staked_principal)
+    }
+
+    pub(crate) fn internal_unstake(&self, amount: u128) -> Promise {
+        ext_token_staking::unstake(
+            amount.into(),
+            self.staking_pool.as_ref().expect("ERR_NOT_STAKING"),
+            0,
+            GAS_FOR_UNSTAKE
+        )
+    }
+
+    pub(crate) fn internal_on_unstaked(&mut self, amount: u128) {
+        self.staked_principal = self.staked_principal.checked_sub(amount).expect("ERR_INTEGER_OVERFLOW");
+        if self.staked_principal == 0 {
+            self.staking_pool = None;
+        }
+    }
+}
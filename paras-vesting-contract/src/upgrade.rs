@@ -0,0 +1,142 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LazyOption;
+use near_sdk::json_types::{Base58CryptoHash, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId, CryptoHash, Promise};
+
+use crate::utils::{assert_one_yocto_or_contract_caller, GAS_FOR_MIGRATE, UPGRADE_DELAY};
+use crate::{Contract, StorageKey};
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StagedUpgrade {
+    pub code_hash: Base58CryptoHash,
+    pub staged_at: U64,
+    pub deployable_at: U64,
+    // paid for the storage of the code, gets it back when the upgrade is removed
+    pub staged_by: AccountId,
+}
+
+// The staged code is visible for UPGRADE_DELAY before it can be deployed,
+// so recipients can check its hash and leave before it lands.
+#[near_bindgen]
+impl Contract {
+    // the input is the raw wasm, the caller pays for its storage
+    #[payable]
+    pub fn stage_upgrade(&mut self) -> StagedUpgrade {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        self.assert_multisig_disabled();
+        let code = env::input().expect("ERR_NO_CODE");
+
+        internal_stage_upgrade(code)
+    }
+
+    // with multisig enabled, a signer stages the code a StageUpgrade proposal approved
+    #[payable]
+    pub fn stage_approved_upgrade(&mut self) -> StagedUpgrade {
+        self.assert_multisig_signer();
+        let code = env::input().expect("ERR_NO_CODE");
+        let approved_code_hash: CryptoHash = approved_upgrade_storage().get().expect("ERR_NO_APPROVED_UPGRADE").into();
+        assert_eq!(env::sha256(&code), approved_code_hash.to_vec(), "ERR_CODE_HASH_NOT_APPROVED");
+        approved_upgrade_storage().remove();
+
+        internal_stage_upgrade(code)
+    }
+
+    pub fn staged_upgrade(&self) -> Option<StagedUpgrade> {
+        staged_upgrade_storage().get()
+    }
+
+    pub fn approved_upgrade(&self) -> Option<Base58CryptoHash> {
+        approved_upgrade_storage().get()
+    }
+
+    #[payable]
+    pub fn cancel_upgrade(&mut self) {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+
+        self.internal_remove_staged_upgrade();
+    }
+
+    // migrate runs right after the deploy, a failing migrate reverts the deploy too
+    #[payable]
+    pub fn deploy_upgrade(&mut self) -> Promise {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+        self.assert_multisig_disabled();
+
+        self.internal_deploy_upgrade()
+    }
+}
+
+impl Contract {
+    // a StageUpgrade proposal only approves the hash, the code is too large for a proposal
+    pub(crate) fn internal_approve_upgrade(&mut self, code_hash: Base58CryptoHash) {
+        approved_upgrade_storage().set(&code_hash);
+    }
+
+    pub(crate) fn internal_deploy_upgrade(&mut self) -> Promise {
+        let upgrade = staged_upgrade_storage().get().expect("ERR_NO_STAGED_UPGRADE");
+        assert!(env::block_timestamp() >= upgrade.deployable_at.0, "ERR_UPGRADE_TIMELOCKED");
+
+        let code = staged_code_storage().get().unwrap();
+        self.internal_remove_staged_upgrade();
+
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call(b"migrate".to_vec(), b"{}".to_vec(), 0, GAS_FOR_MIGRATE)
+    }
+
+    // the storage paid on staging goes back to whoever staged the code
+    fn internal_remove_staged_upgrade(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let upgrade = staged_upgrade_storage().get().expect("ERR_NO_STAGED_UPGRADE");
+        staged_upgrade_storage().remove();
+        staged_code_storage().remove();
+
+        let refund = (initial_storage_usage - env::storage_usage()) as u128 * env::storage_byte_cost();
+        if refund > 0 {
+            Promise::new(upgrade.staged_by).transfer(refund);
+        }
+    }
+}
+
+fn internal_stage_upgrade(code: Vec<u8>) -> StagedUpgrade {
+    assert!(!code.is_empty(), "ERR_NO_CODE");
+    assert!(staged_upgrade_storage().is_none(), "ERR_UPGRADE_ALREADY_STAGED");
+
+    let initial_storage_usage = env::storage_usage();
+    let mut code_hash = CryptoHash::default();
+    code_hash.copy_from_slice(&env::sha256(&code));
+    let upgrade = StagedUpgrade {
+        code_hash: code_hash.into(),
+        staged_at: env::block_timestamp().into(),
+        deployable_at: env::block_timestamp().checked_add(UPGRADE_DELAY).expect("ERR_INTEGER_OVERFLOW").into(),
+        staged_by: env::predecessor_account_id(),
+    };
+    staged_code_storage().set(&code);
+    staged_upgrade_storage().set(&upgrade);
+
+    let storage_cost = (env::storage_usage() - initial_storage_usage) as u128 * env::storage_byte_cost();
+    assert!(env::attached_deposit() >= storage_cost, "ERR_NOT_ENOUGH_DEPOSIT_FOR_STORAGE");
+    let refund = env::attached_deposit() - storage_cost;
+    if refund > 0 {
+        Promise::new(env::predecessor_account_id()).transfer(refund);
+    }
+    upgrade
+}
+
+// kept out of the Contract struct, so the state layout doesn't change
+// and the code isn't loaded on every call
+fn staged_upgrade_storage() -> LazyOption<StagedUpgrade> {
+    LazyOption::new(StorageKey::StagedUpgrade, None)
+}
+
+fn staged_code_storage() -> LazyOption<Vec<u8>> {
+    LazyOption::new(StorageKey::StagedUpgradeCode, None)
+}
+
+fn approved_upgrade_storage() -> LazyOption<Base58CryptoHash> {
+    LazyOption::new(StorageKey::ApprovedUpgradeHash, None)
+}
@@ -1,19 +1,157 @@
-use near_sdk::{ext_contract, Gas};
+use near_sdk::{assert_one_yocto, env, ext_contract, Balance, Gas, PromiseResult};
 use near_sdk::json_types::{U128};
 
+use crate::multi_token::MAX_EXTRA_TOKENS;
+use crate::split::SplitGrant;
+
 pub const GAS_FOR_FT_TRANSFER: Gas = 10_000_000_000_000;
+pub const GAS_FOR_STORAGE_BALANCE_OF: Gas = 5_000_000_000_000;
+pub const GAS_FOR_RESOLVE_CLAIM: Gas = 5_000_000_000_000;
+pub const GAS_FOR_RESOLVE_PUSH: Gas = 10_000_000_000_000;
+pub const GAS_FOR_FT_TRANSFER_CALL: Gas = 50_000_000_000_000;
+// the main token and every extra token are transferred from the callback
+pub const GAS_FOR_CLAIM_VESTED_TO: Gas = (GAS_FOR_FT_TRANSFER + GAS_FOR_RESOLVE_CLAIM) * (MAX_EXTRA_TOKENS as u64 + 1) + 10_000_000_000_000;
+pub const GAS_FOR_UNSTAKE: Gas = 40_000_000_000_000;
+pub const GAS_FOR_DEPOSIT_AND_STAKE: Gas = 50_000_000_000_000;
+pub const GAS_FOR_WITHDRAW: Gas = 30_000_000_000_000;
+pub const GAS_FOR_CLAIM_REWARD: Gas = 40_000_000_000_000;
+pub const GAS_FOR_RESOLVE_STAKING: Gas = 10_000_000_000_000;
+// settles the grant with up to two transfers per token
+pub const GAS_FOR_RESOLVE_REVOKE: Gas = 120_000_000_000_000;
+pub const GAS_FOR_NFT_ON_TRANSFER: Gas = 25_000_000_000_000;
+pub const GAS_FOR_NFT_RESOLVE_TRANSFER: Gas = 10_000_000_000_000;
+pub const GAS_FOR_NFT_ON_APPROVE: Gas = 25_000_000_000_000;
+pub const GAS_FOR_MIGRATE: Gas = 50_000_000_000_000;
+pub const GAS_FOR_FT_BALANCE_OF: Gas = 5_000_000_000_000;
+pub const GAS_FOR_STORAGE_UNREGISTER: Gas = 10_000_000_000_000;
+// unregisters from every token before deleting the account
+pub const GAS_FOR_RESOLVE_FINALIZE: Gas = GAS_FOR_STORAGE_UNREGISTER * (MAX_EXTRA_TOKENS as u64 + 1) + 20_000_000_000_000;
+pub const GAS_FOR_NEW_SPLIT_GRANT: Gas = 20_000_000_000_000;
+pub const GAS_FOR_STORAGE_DEPOSIT: Gas = 10_000_000_000_000;
+pub const GAS_FOR_RESOLVE_FUND_SPLIT: Gas = 10_000_000_000_000;
+// registers and funds a fungible token child
+pub const GAS_FOR_RESOLVE_SPLIT: Gas = GAS_FOR_STORAGE_DEPOSIT + GAS_FOR_FT_TRANSFER + GAS_FOR_RESOLVE_FUND_SPLIT + 5_000_000_000_000;
 pub const ONE_MONTH: u64 = 2629746000000000; // 30.436875*24*60*60*10**9
+pub const ONE_DAY: u64 = 86400000000000;
+pub const MAX_EMERGENCY_PAUSE_DURATION: u64 = ONE_DAY * 7;
+pub const EMERGENCY_PAUSE_COOLDOWN: u64 = ONE_DAY;
+pub const MULTISIG_PROPOSAL_DURATION: u64 = ONE_DAY * 7;
+pub const MAX_MULTISIG_SIGNERS: usize = 10;
+// recipients get this long to check a staged upgrade
+pub const UPGRADE_DELAY: u64 = ONE_DAY * 7;
+// kept on top of the storage in use when a native grant is created, for checkpoints and other state added later
+pub const NATIVE_STORAGE_RESERVE: Balance = 1_000_000_000_000_000_000_000_000;
+// state, reserve and token registration of a child grant, on top of its code
+pub const SPLIT_GRANT_STORAGE_DEPOSIT: Balance = 2_000_000_000_000_000_000_000_000;
+// NEP-145 registration of a child grant with the token
+pub const STORAGE_DEPOSIT_FOR_FT: Balance = 12_500_000_000_000_000_000_000;
 //pub const NANO_SECONDS_PER_MONTH: u64 = 2628000_000_000_000;
 
+// value * numerator / denominator without overflowing on token amounts, assumes numerator <= denominator
+pub fn mul_div(value: u128, numerator: u128, denominator: u128) -> u128 {
+    let (high, low) = mul_wide(value, numerator);
+    // long division of the 256 bit product, the quotient fits in 128 bits
+    let mut remainder: u128 = high % denominator;
+    let mut quotient: u128 = 0;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1;
+        }
+    }
+    quotient
+}
+
+// full 256 bit product as (high, low)
+fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    let mask = u64::MAX as u128;
+    let (a_high, a_low) = (a >> 64, a & mask);
+    let (b_high, b_low) = (b >> 64, b & mask);
+
+    let low_low = a_low * b_low;
+    let high_low = a_high * b_low;
+    let low_high = a_low * b_high;
+    let high_high = a_high * b_high;
+
+    let middle = (low_low >> 64) + (high_low & mask) + (low_high & mask);
+    let low = (low_low & mask) | (middle << 64);
+    let high = high_high + (high_low >> 64) + (low_high >> 64) + (middle >> 64);
+    (high, low)
+}
+
+// A contract owner, like a Sputnik DAO executing a FunctionCall proposal, can't be acting through
+// a function call access key, so it doesn't have to attach the yoctoNEAR.
+pub fn assert_one_yocto_or_contract_caller() {
+    if env::predecessor_account_id() == env::signer_account_id() {
+        assert_one_yocto();
+    }
+}
+
+pub fn is_promise_success() -> bool {
+    assert_eq!(env::promise_results_count(), 1, "ERR_TOO_MANY_RESULTS");
+    match env::promise_result(0) {
+        PromiseResult::Successful(_) => true,
+        _ => false,
+    }
+}
+
 #[ext_contract(ext_fungible_token)]
 pub trait FungibleToken {
     fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+    fn ft_transfer_call(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>, msg: String);
     fn ft_balance_of(&self, account_id: AccountId);
 }
 
+#[ext_contract(ext_storage_management)]
+pub trait StorageManagement {
+    fn storage_balance_of(&self, account_id: AccountId);
+    fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>);
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool;
+}
+
+// whitelisted pool for the main token, tokens are staked with ft_transfer_call
+#[ext_contract(ext_token_staking)]
+pub trait TokenStaking {
+    // sends the unstaked amount back to the caller with ft_transfer
+    fn unstake(&mut self, amount: U128);
+    fn claim_reward(&mut self, receiver_id: AccountId);
+}
+
+// validator staking pool used by native grants
+#[ext_contract(ext_staking_pool)]
+pub trait StakingPool {
+    fn deposit_and_stake(&mut self);
+    fn unstake(&mut self, amount: U128);
+    fn withdraw(&mut self, amount: U128);
+}
+
+#[ext_contract(ext_non_fungible_token_receiver)]
+pub trait NonFungibleTokenReceiver {
+    fn nft_on_transfer(&mut self, sender_id: AccountId, previous_owner_id: AccountId, token_id: String, msg: String) -> bool;
+}
+
+#[ext_contract(ext_non_fungible_approval_receiver)]
+pub trait NonFungibleTokenApprovalReceiver {
+    fn nft_on_approve(&mut self, token_id: String, owner_id: AccountId, approval_id: u64, msg: String);
+}
+
 #[ext_contract(ext_self)]
 pub trait Vesting {
-    fn callback_revoke(
-        &mut self,
-    );
+    fn callback_revoke(&mut self, amount: U128);
+    fn callback_claim_vested(&mut self, amount: U128);
+    fn callback_claim_vested_to(&mut self, receiver_id: AccountId, amount: U128);
+    fn callback_claim_vested_call(&mut self, amount: U128);
+    fn callback_claim_extra_token(&mut self, token_index: u64, amount: U128);
+    fn callback_stake(&mut self, amount: U128);
+    fn callback_unstake(&mut self, amount: U128);
+    fn callback_deposit_and_stake(&mut self, amount: U128);
+    fn callback_withdraw_from_staking_pool(&mut self, amount: U128);
+    fn callback_push_vested(&mut self, keeper_id: AccountId, amount: U128, keeper_reward: U128);
+    fn nft_resolve_transfer(&mut self, previous_owner_id: AccountId, receiver_id: AccountId, token_id: String);
+    fn callback_finalize(&mut self);
+    fn callback_split_grant(&mut self, account_id: AccountId, grant: SplitGrant, deposit: U128);
+    fn callback_fund_split_grant(&mut self, account_id: AccountId, grant: SplitGrant);
 }
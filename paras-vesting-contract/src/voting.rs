@@ -0,0 +1,211 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64, ValidAccountId};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Promise};
+
+use crate::utils::{assert_one_yocto_or_contract_caller, mul_div};
+use crate::{amount_vested_on_curve, amount_vested_with_acceleration, Contract, GrantType, VestingCurve};
+
+// state of the grant for governance, recorded whenever the voter, the claimed amount or the schedule changes
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Checkpoint {
+    pub timestamp: U64,
+    pub voter: AccountId,
+    pub unvested: U128,
+    pub vested_unclaimed: U128,
+    // weight at the time, so a later change doesn't rewrite past voting power
+    pub unvested_voting_weight: u8,
+    // the schedule at the time, vesting keeps going until the next checkpoint
+    pub amount: U128,
+    pub amount_claimed: U128,
+    pub amount_accelerated: U128,
+    pub amount_on_curve: U128,
+    pub amount_approved: U128,
+    pub start: U64,
+    pub duration: U64,
+    pub cliff: U64,
+    pub curve: VestingCurve,
+    pub grant_type: GrantType,
+    pub paused_duration: U64,
+    pub is_vesting_paused: bool,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VotingPower {
+    pub unvested: U128,
+    pub vested_unclaimed: U128,
+    // vested_unclaimed + unvested * unvested_voting_weight / 100
+    pub weighted_total: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    // percentage (0-100) of the unvested amount counted as voting power, 0 doesn't count locked tokens at all
+    #[payable]
+    pub fn set_unvested_voting_weight(&mut self, percent: u8) {
+        assert_eq!(self.owner(), env::predecessor_account_id(), "ERR_NOT_OWNER");
+        assert_one_yocto_or_contract_caller();
+        assert!(percent <= 100, "ERR_INVALID_PERCENT");
+
+        self.unvested_voting_weight = percent;
+        self.internal_checkpoint();
+    }
+
+    pub fn unvested_voting_weight(&self) -> u8 {
+        self.unvested_voting_weight
+    }
+
+    // None gives the voting power back to the recipient
+    #[payable]
+    pub fn delegate_voting_power(&mut self, delegate: Option<ValidAccountId>) {
+        assert_one_yocto();
+        assert_eq!(env::predecessor_account_id(), self.recipient(), "ERR_CALLER_NOT_RECIPIENT");
+
+        self.voting_delegate = delegate.map(|delegate| delegate.into());
+        self.internal_checkpoint();
+    }
+
+    pub fn voting_delegate(&self) -> Option<AccountId> {
+        self.voting_delegate.clone()
+    }
+
+    pub fn voter(&self) -> AccountId {
+        self.voting_delegate.clone().unwrap_or(self.recipient())
+    }
+
+    // Current values when at_timestamp is None or not in the past, otherwise the schedule of
+    // the last checkpoint before at_timestamp, vested up to at_timestamp.
+    pub fn voting_power(&self, account_id: ValidAccountId, at_timestamp: Option<U64>) -> VotingPower {
+        let (checkpoint, timestamp) = match at_timestamp {
+            Some(at_timestamp) if at_timestamp.0 < env::block_timestamp() => {
+                match self.internal_checkpoint_at(at_timestamp.0) {
+                    Some(checkpoint) => (checkpoint, at_timestamp.0),
+                    None => return weighted_voting_power(0, 0, 0),
+                }
+            }
+            _ => (self.internal_current_checkpoint(), env::block_timestamp()),
+        };
+
+        if checkpoint.voter != *account_id.as_ref() {
+            return weighted_voting_power(0, 0, 0);
+        }
+        let amount_vested = checkpoint.amount_vested_at(timestamp);
+        weighted_voting_power(
+            checkpoint.amount.0.saturating_sub(amount_vested),
+            amount_vested.saturating_sub(checkpoint.amount_claimed.0),
+            checkpoint.unvested_voting_weight
+        )
+    }
+
+    pub fn checkpoints(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<Checkpoint> {
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(self.checkpoints.len());
+        (from_index..std::cmp::min(from_index.saturating_add(limit), self.checkpoints.len()))
+            .map(|index| self.checkpoints.get(index).unwrap())
+            .collect()
+    }
+
+    // lets anyone (e.g. a snapshot service) record the current state, the caller pays for the storage
+    #[payable]
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        let initial_storage_usage = env::storage_usage();
+        let checkpoint = self.internal_checkpoint();
+
+        let storage_cost = env::storage_usage().saturating_sub(initial_storage_usage) as u128 * env::storage_byte_cost();
+        assert!(env::attached_deposit() >= storage_cost, "ERR_NOT_ENOUGH_DEPOSIT_FOR_STORAGE");
+        let refund = env::attached_deposit() - storage_cost;
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+        checkpoint
+    }
+}
+
+impl Contract {
+    fn internal_current_checkpoint(&self) -> Checkpoint {
+        let amount_vested = self.internal_calculate_amount_vested();
+        Checkpoint {
+            timestamp: env::block_timestamp().into(),
+            voter: self.voter(),
+            unvested: self.amount.saturating_sub(amount_vested).into(),
+            vested_unclaimed: amount_vested.saturating_sub(self.amount_claimed).into(),
+            unvested_voting_weight: self.unvested_voting_weight,
+            amount: self.amount.into(),
+            amount_claimed: self.amount_claimed.into(),
+            amount_accelerated: self.amount_accelerated.into(),
+            amount_on_curve: self.internal_calculate_amount_on_curve().into(),
+            amount_approved: self.internal_calculate_amount_approved().into(),
+            start: self.start.into(),
+            duration: self.duration.into(),
+            cliff: self.cliff.into(),
+            curve: self.curve.clone(),
+            grant_type: self.grant_type.clone(),
+            paused_duration: self.internal_paused_duration(env::block_timestamp()).into(),
+            is_vesting_paused: self.is_vesting_paused(),
+        }
+    }
+
+    // one checkpoint per block at most, a later change in the same block replaces it
+    pub(crate) fn internal_checkpoint(&mut self) -> Checkpoint {
+        let checkpoint = self.internal_current_checkpoint();
+        let len = self.checkpoints.len();
+        if len > 0 && self.checkpoints.get(len - 1).unwrap().timestamp.0 == checkpoint.timestamp.0 {
+            self.checkpoints.replace(len - 1, &checkpoint);
+        } else {
+            self.checkpoints.push(&checkpoint);
+        }
+        checkpoint
+    }
+
+    // binary search for the last checkpoint at or before timestamp
+    fn internal_checkpoint_at(&self, timestamp: u64) -> Option<Checkpoint> {
+        let (mut low, mut high) = (0, self.checkpoints.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.checkpoints.get(mid).unwrap().timestamp.0 <= timestamp {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        if low == 0 { None } else { self.checkpoints.get(low - 1) }
+    }
+}
+
+impl Checkpoint {
+    // nothing changed the schedule between the checkpoint and timestamp, a pause or
+    // a resume would have recorded a checkpoint
+    fn amount_vested_at(&self, timestamp: u64) -> u128 {
+        let mut paused_duration = self.paused_duration.0;
+        if self.is_vesting_paused {
+            paused_duration += timestamp.saturating_sub(std::cmp::max(self.timestamp.0, self.start.0));
+        }
+        let amount_vested_on_curve = amount_vested_on_curve(
+            self.amount_on_curve.0,
+            timestamp - paused_duration,
+            self.start.0,
+            self.cliff.0,
+            self.duration.0,
+            &self.curve
+        );
+        let amount_vested_on_full_schedule = match self.grant_type {
+            GrantType::Time => amount_vested_on_curve,
+            GrantType::Milestone => self.amount_approved.0,
+            GrantType::Hybrid => std::cmp::min(amount_vested_on_curve, self.amount_approved.0),
+        };
+        amount_vested_with_acceleration(self.amount.0, self.amount_accelerated.0, amount_vested_on_full_schedule)
+    }
+}
+
+fn weighted_voting_power(unvested: u128, vested_unclaimed: u128, unvested_voting_weight: u8) -> VotingPower {
+    VotingPower {
+        unvested: unvested.into(),
+        vested_unclaimed: vested_unclaimed.into(),
+        weighted_total: vested_unclaimed
+            .checked_add(mul_div(unvested, unvested_voting_weight as u128, 100))
+            .expect("ERR_INTEGER_OVERFLOW")
+            .into(),
+    }
+}
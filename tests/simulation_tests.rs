@@ -1,14 +1,15 @@
-use near_sdk::json_types::{U128, U64};
+use near_sdk::json_types::{Base64VecU8, U128, U64};
 use near_sdk::{AccountId};
 use near_sdk::serde_json::json;
 use near_sdk::serde_json;
-use near_sdk_sim::{call, transaction::ExecutionStatus, view, DEFAULT_GAS, UserAccount};
+use near_sdk_sim::{call, to_yocto, transaction::ExecutionStatus, view, DEFAULT_GAS, UserAccount};
 use chrono::{TimeZone, Utc};
+use paras_vesting_contract::StagedUpgrade;
 
 
 // use utils::{init as init, register_user};
 use crate::utils::{
-    init, ptoy, ytop, SIX_MONTHS, TWO_YEARS, JUNE_1_2021, ONE_MILLION_COIN, ONE_MONTH, OCTOBER_1_2021
+    init, init_dao, init_dao_grant, init_native, init_staking_pool, register_user, vesting_wasm, ptoy, ytop, SIX_MONTHS, TWO_YEARS, JUNE_1_2021, ONE_MILLION_COIN, ONE_MONTH, OCTOBER_1_2021
 };
 mod utils;
 
@@ -112,7 +113,7 @@ fn simulate_claim_vested() {
 
     let outcome = call!(
         alice,
-        vesting.claim_vested(),
+        vesting.claim_vested(None),
         deposit = 1
     );
 
@@ -132,7 +133,7 @@ fn simulate_claim_vested() {
 
     let outcome = call!(
         alice,
-        vesting.claim_vested(),
+        vesting.claim_vested(None),
         deposit = 1
     );
     assert_eq!(outcome.promise_errors().len(), 0);
@@ -144,7 +145,7 @@ fn simulate_claim_vested() {
     root.borrow_runtime_mut().cur_block.block_timestamp = JUNE_1_2021 + TWO_YEARS;
     call!(
         alice,
-        vesting.claim_vested(),
+        vesting.claim_vested(None),
         deposit = 1
     );
     let alice_balance: u128 = get_balance(&alice, ft.account_id()).into();
@@ -158,7 +159,7 @@ fn simulate_claim_vested_one_month() {
 
     let outcome = call!(
         alice,
-        vesting.claim_vested(),
+        vesting.claim_vested(None),
         deposit = 1
     );
 
@@ -178,7 +179,7 @@ fn simulate_claim_vested_one_month() {
 
     let outcome = call!(
         alice,
-        vesting.claim_vested(),
+        vesting.claim_vested(None),
         deposit = 1
     );
     assert_eq!(outcome.promise_errors().len(), 0);
@@ -189,13 +190,44 @@ fn simulate_claim_vested_one_month() {
     root.borrow_runtime_mut().cur_block.block_timestamp = OCTOBER_1_2021 + TWO_YEARS;
     call!(
         alice,
-        vesting.claim_vested(),
+        vesting.claim_vested(None),
         deposit = 1
     );
     let alice_balance: u128 = get_balance(&alice, ft.account_id()).into();
     assert_eq!(ytop(alice_balance), 1_000_000);
 }
 
+#[test]
+fn simulate_claim_vested_partial() {
+    let (root, ft, vesting, alice) = init(false);
+    send_amount(&ft, &root, &vesting.user_account);
+
+    // 1_000_000*6/24 is vested right after the cliff
+    root.borrow_runtime_mut().cur_block.block_timestamp = JUNE_1_2021 + SIX_MONTHS + 10;
+
+    let outcome = call!(
+        alice,
+        vesting.claim_vested(Some(U128::from(ptoy(50_000)))),
+        deposit = 1
+    );
+    assert_eq!(outcome.promise_errors().len(), 0);
+    let alice_balance: u128 = get_balance(&alice, ft.account_id()).into();
+    assert_eq!(ytop(alice_balance), 50_000);
+
+    let releasable_amount: U128 = view!(vesting.releasable_amount()).unwrap_json();
+    assert_eq!(releasable_amount, U128::from(ONE_MILLION_COIN * 6 / 24 - ptoy(50_000)));
+
+    // the rest is claimed after vesting is over
+    root.borrow_runtime_mut().cur_block.block_timestamp = JUNE_1_2021 + TWO_YEARS;
+    call!(
+        alice,
+        vesting.claim_vested(None),
+        deposit = 1
+    );
+    let alice_balance: u128 = get_balance(&alice, ft.account_id()).into();
+    assert_eq!(alice_balance, ONE_MILLION_COIN);
+}
+
 /*
 you can use `.borrow_runtime_mut()` on any UserAccount object
  in sim tests and then modify `cur_block.block_timestamp`
@@ -251,4 +283,322 @@ fn simulate_revoke() {
     assert_eq!(root_balance_after, U128::from(root_balance_before.0 + ONE_MILLION_COIN * 18/24));
     assert_eq!(vesting_balance_after, U128::from(0));
  
-}
\ No newline at end of file
+}
+
+#[test]
+fn simulate_claim_vested_to() {
+    let (root, ft, vesting, alice) = init(false);
+    send_amount(&ft, &root, &vesting.user_account);
+
+    let cold_wallet = root.create_user(
+        "cold-wallet".to_string(),
+        to_yocto("10")
+    );
+
+    root.borrow_runtime_mut().cur_block.block_timestamp = JUNE_1_2021 + SIX_MONTHS + 10;
+
+    // receiver is not registered on the token yet
+    let outcome = call!(
+        alice,
+        vesting.claim_vested_to(cold_wallet.valid_account_id(), None),
+        deposit = 1
+    );
+    assert_eq!(outcome.promise_errors().len(), 1);
+
+    if let ExecutionStatus::Failure(execution_error) =
+        &outcome.promise_errors().remove(0).unwrap().outcome().status
+    {
+        assert!(execution_error.to_string().contains("ERR_RECEIVER_NOT_REGISTERED"));
+    } else {
+        unreachable!();
+    }
+
+    let amount_claimed: U128 = view!(vesting.amount_claimed()).unwrap_json();
+    assert_eq!(amount_claimed, U128::from(0));
+
+    register_user(&cold_wallet);
+
+    let outcome = call!(
+        alice,
+        vesting.claim_vested_to(cold_wallet.valid_account_id(), Some(U128::from(ptoy(100_000)))),
+        deposit = 1
+    );
+    assert_eq!(outcome.promise_errors().len(), 0);
+
+    let cold_wallet_balance: u128 = get_balance(&cold_wallet, ft.account_id()).into();
+    assert_eq!(ytop(cold_wallet_balance), 100_000);
+    let alice_balance: u128 = get_balance(&alice, ft.account_id()).into();
+    assert_eq!(alice_balance, 0);
+
+    let amount_claimed: U128 = view!(vesting.amount_claimed()).unwrap_json();
+    assert_eq!(amount_claimed, U128::from(ptoy(100_000)));
+}
+
+#[test]
+fn simulate_claim_vested_call_refund() {
+    let (root, ft, vesting, alice) = init(false);
+    send_amount(&ft, &root, &vesting.user_account);
+
+    // a registered account without a contract, so ft_on_transfer fails and everything is refunded
+    let receiver = root.create_user(
+        "receiver".to_string(),
+        to_yocto("10")
+    );
+    register_user(&receiver);
+
+    root.borrow_runtime_mut().cur_block.block_timestamp = JUNE_1_2021 + SIX_MONTHS + 10;
+
+    let outcome = call!(
+        alice,
+        vesting.claim_vested_call(receiver.valid_account_id(), None, "".to_string()),
+        deposit = 1,
+        gas = DEFAULT_GAS
+    );
+    let amount_used: U128 = outcome.unwrap_json();
+    assert_eq!(amount_used, U128::from(0));
+
+    let receiver_balance: U128 = get_balance(&receiver, ft.account_id());
+    assert_eq!(receiver_balance, U128::from(0));
+    let vesting_balance: U128 = get_balance(&vesting.user_account, ft.account_id());
+    assert_eq!(vesting_balance, U128::from(ONE_MILLION_COIN));
+
+    // refunded amount is releasable again
+    let amount_claimed: U128 = view!(vesting.amount_claimed()).unwrap_json();
+    assert_eq!(amount_claimed, U128::from(0));
+    let releasable_amount: U128 = view!(vesting.releasable_amount()).unwrap_json();
+    assert_eq!(releasable_amount, U128::from(ONE_MILLION_COIN * 6 / 24));
+}
+
+#[test]
+fn simulate_native_claim_vested() {
+    let (root, _, _, alice) = init(false);
+    let vesting_native = init_native(&root, &alice, to_yocto("10"), to_yocto("48"));
+
+    let amount: U128 = vesting_native.view(vesting_native.account_id(), "amount", b"").unwrap_json();
+    assert_eq!(amount, U128::from(to_yocto("48")));
+    let token: Option<AccountId> = vesting_native.view(vesting_native.account_id(), "token", b"").unwrap_json();
+    assert_eq!(token, None);
+
+    root.borrow_runtime_mut().cur_block.block_timestamp = JUNE_1_2021 + SIX_MONTHS + 10;
+
+    let alice_balance_before = alice.account().unwrap().amount;
+    alice.call(
+        vesting_native.account_id(),
+        "claim_vested",
+        &json!({}).to_string().into_bytes(),
+        DEFAULT_GAS,
+        1,
+    ).assert_success();
+    let alice_balance_after = alice.account().unwrap().amount;
+
+    // 6 of 24 months, minus gas
+    assert!(alice_balance_after > alice_balance_before + to_yocto("11.9"));
+    assert!(alice_balance_after < alice_balance_before + to_yocto("12"));
+
+    let amount_claimed: U128 = vesting_native.view(vesting_native.account_id(), "amount_claimed", b"").unwrap_json();
+    assert_eq!(amount_claimed, U128::from(to_yocto("12")));
+
+    // storage balance is untouched by the payout
+    assert!(vesting_native.account().unwrap().amount >= to_yocto("10") + to_yocto("36"));
+}
+
+#[test]
+fn simulate_native_staking() {
+    let (root, _, _, alice) = init(false);
+    let vesting_native = init_native(&root, &alice, to_yocto("10"), to_yocto("48"));
+    let staking_pool = init_staking_pool(&root);
+
+    root.call(
+        vesting_native.account_id(),
+        "add_staking_pool",
+        &json!({"pool_id": staking_pool.valid_account_id()}).to_string().into_bytes(),
+        DEFAULT_GAS,
+        1,
+    ).assert_success();
+
+    let outcome = alice.call(
+        vesting_native.account_id(),
+        "deposit_and_stake",
+        &json!({"pool_id": staking_pool.valid_account_id(), "amount": U128::from(to_yocto("40"))}).to_string().into_bytes(),
+        DEFAULT_GAS,
+        1,
+    );
+    assert_eq!(outcome.promise_errors().len(), 0);
+
+    let staked_principal: U128 = vesting_native.view(vesting_native.account_id(), "staked_principal", b"").unwrap_json();
+    assert_eq!(staked_principal, U128::from(to_yocto("40")));
+    let staked_balance: U128 = staking_pool.view(
+        staking_pool.account_id(),
+        "get_account_staked_balance",
+        &json!({"account_id": vesting_native.valid_account_id()}).to_string().into_bytes(),
+    ).unwrap_json();
+    assert_eq!(staked_balance, U128::from(to_yocto("40")));
+
+    // everything vested, but only the liquid part can be claimed
+    root.borrow_runtime_mut().cur_block.block_timestamp = JUNE_1_2021 + TWO_YEARS;
+    alice.call(
+        vesting_native.account_id(),
+        "claim_vested",
+        &json!({}).to_string().into_bytes(),
+        DEFAULT_GAS,
+        1,
+    ).assert_success();
+    let amount_claimed: U128 = vesting_native.view(vesting_native.account_id(), "amount_claimed", b"").unwrap_json();
+    assert_eq!(amount_claimed, U128::from(to_yocto("8")));
+
+    root.call(
+        staking_pool.account_id(),
+        "add_reward",
+        &json!({"account_id": vesting_native.valid_account_id()}).to_string().into_bytes(),
+        DEFAULT_GAS,
+        to_yocto("2"),
+    ).assert_success();
+
+    alice.call(
+        vesting_native.account_id(),
+        "unstake",
+        &json!({"pool_id": staking_pool.valid_account_id(), "amount": U128::from(to_yocto("42"))}).to_string().into_bytes(),
+        DEFAULT_GAS,
+        1,
+    ).assert_success();
+
+    let alice_balance_before = alice.account().unwrap().amount;
+    let outcome = alice.call(
+        vesting_native.account_id(),
+        "withdraw_from_staking_pool",
+        &json!({"pool_id": staking_pool.valid_account_id(), "amount": U128::from(to_yocto("42"))}).to_string().into_bytes(),
+        DEFAULT_GAS,
+        1,
+    );
+    assert_eq!(outcome.promise_errors().len(), 0);
+    let alice_balance_after = alice.account().unwrap().amount;
+
+    // principal is back in the contract, rewards went to the recipient
+    let staked_principal: U128 = vesting_native.view(vesting_native.account_id(), "staked_principal", b"").unwrap_json();
+    assert_eq!(staked_principal, U128::from(0));
+    let liquid_balance: U128 = vesting_native.view(vesting_native.account_id(), "liquid_balance", b"").unwrap_json();
+    assert_eq!(liquid_balance, U128::from(to_yocto("40")));
+    assert!(alice_balance_after > alice_balance_before + to_yocto("1.9"));
+}
+
+#[test]
+fn simulate_native_finalize() {
+    let (root, _, _, alice) = init(false);
+    let vesting_native = init_native(&root, &alice, to_yocto("10"), to_yocto("48"));
+
+    root.borrow_runtime_mut().cur_block.block_timestamp = JUNE_1_2021 + TWO_YEARS;
+
+    alice.call(
+        vesting_native.account_id(),
+        "claim_vested",
+        &json!({}).to_string().into_bytes(),
+        DEFAULT_GAS,
+        1,
+    ).assert_success();
+
+    let root_balance_before = root.account().unwrap().amount;
+    root.call(
+        vesting_native.account_id(),
+        "finalize",
+        &json!({}).to_string().into_bytes(),
+        DEFAULT_GAS,
+        1,
+    ).assert_success();
+
+    // the storage stake goes back to the owner
+    assert!(vesting_native.account().is_none());
+    assert!(root.account().unwrap().amount > root_balance_before + to_yocto("9"));
+}
+
+// needs res/sputnikdao2.wasm, see Testing in the README
+#[test]
+#[ignore]
+fn simulate_dao_revoke() {
+    let (root, ft, _, alice) = init(false);
+    let dao = init_dao(&root, &root);
+    register_user(&dao);
+
+    let vesting = init_dao_grant(&root, &dao, &alice);
+    send_amount(&ft, &root, &vesting);
+
+    root.borrow_runtime_mut().cur_block.block_timestamp = JUNE_1_2021 + SIX_MONTHS;
+
+    // the DAO calls revoke without the 1 yoctoNEAR deposit
+    root.call(
+        dao.account_id(),
+        "add_proposal",
+        &json!({
+            "proposal": {
+                "description": "Revoke grant",
+                "kind": {
+                    "FunctionCall": {
+                        "receiver_id": vesting.account_id(),
+                        "actions": [{
+                            "method_name": "revoke",
+                            "args": Base64VecU8::from(b"{}".to_vec()),
+                            "deposit": U128::from(0),
+                            "gas": U64::from(100_000_000_000_000),
+                        }],
+                    }
+                },
+            }
+        }).to_string().into_bytes(),
+        DEFAULT_GAS,
+        to_yocto("1"), // proposal bond
+    ).assert_success();
+
+    root.call(
+        dao.account_id(),
+        "act_proposal",
+        &json!({"id": 0, "action": "VoteApprove"}).to_string().into_bytes(),
+        DEFAULT_GAS,
+        0,
+    ).assert_success();
+
+    let start: U64 = root.view(vesting.account_id(), "start", b"").unwrap_json();
+    assert_eq!(start, U64::from(0));
+    assert_eq!(get_balance(&dao, ft.account_id()), U128::from(ONE_MILLION_COIN * 18 / 24));
+    assert_eq!(get_balance(&alice, ft.account_id()), U128::from(ONE_MILLION_COIN * 6 / 24));
+}
+
+#[test]
+fn simulate_deploy_upgrade() {
+    let (root, ft, vesting, alice) = init(false);
+    send_amount(&ft, &root, &vesting.user_account);
+
+    root.borrow_runtime_mut().cur_block.block_timestamp = JUNE_1_2021;
+    root.call(
+        vesting.account_id(),
+        "stage_upgrade",
+        &vesting_wasm(),
+        DEFAULT_GAS,
+        to_yocto("10"), // storage of the staged code
+    ).assert_success();
+
+    root.borrow_runtime_mut().cur_block.block_timestamp = JUNE_1_2021 + SIX_MONTHS;
+    let outcome = call!(
+        root,
+        vesting.deploy_upgrade(),
+        deposit = 1
+    );
+    outcome.assert_success();
+    // the deploy and the migrate call run as one batch
+    for result in outcome.promise_results().into_iter().flatten() {
+        assert!(result.is_ok());
+    }
+
+    let staged_upgrade: Option<StagedUpgrade> = view!(vesting.staged_upgrade()).unwrap_json();
+    assert!(staged_upgrade.is_none());
+    let recipient: AccountId = view!(vesting.recipient()).unwrap_json();
+    assert_eq!(recipient, alice.account_id);
+    let amount: U128 = view!(vesting.amount()).unwrap_json();
+    assert_eq!(amount, U128::from(ONE_MILLION_COIN));
+
+    // the migrated state keeps working
+    call!(
+        alice,
+        vesting.claim_vested(None),
+        deposit = 1
+    ).assert_success();
+    assert_eq!(get_balance(&alice, ft.account_id()), U128::from(ONE_MILLION_COIN * 6 / 24));
+}
@@ -9,10 +9,15 @@ use near_sdk_sim::{
 near_sdk_sim::lazy_static_include::lazy_static_include_bytes! {
     FT_WASM_BYTES => "res/fungible_token.wasm",
     VESTING_WASM_BYTES => "res/paras_vesting_contract.wasm",
+    MOCK_STAKING_POOL_WASM_BYTES => "res/mock_staking_pool.wasm",
 }
 
 pub const FT_ID: &str = "ft";
 pub const VESTING_ID: &str = "vesting";
+pub const STAKING_POOL_ID: &str = "staking-pool";
+pub const DAO_ID: &str = "dao";
+// not built from this repository, copy it from sputnikdao2/res of near-daos/sputnik-dao-contract
+const SPUTNIKDAO2_WASM_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/res/sputnikdao2.wasm");
 pub const ONE_MONTH: u64 = 2629746000000000; // 30.436875*24*60*60*10**9
 pub const TWO_YEARS: u64 = ONE_MONTH * 12 * 2;
 pub const JUNE_1_2021: u64 = 1622505600000000000; // Tuesday, June 1, 2021 12:00:00 AM GMT
@@ -21,6 +26,11 @@ pub const OCTOBER_1_2021: u64 = 1633046400000000000;
 pub const SIX_MONTHS: u64 = ONE_MONTH * 6;
 pub const ONE_MILLION_COIN: u128 = 1_000_000 * 10u128.pow(18);
 
+// raw wasm of the vesting contract, e.g. to stage it as an upgrade
+pub fn vesting_wasm() -> Vec<u8> {
+    VESTING_WASM_BYTES.to_vec()
+}
+
 /// PARAS to yoctoPARAS
 pub fn ptoy(paras_amount: u128) -> u128 {
     paras_amount * 10u128.pow(18)
@@ -89,7 +99,8 @@ pub fn init(is_one_month: bool) -> (UserAccount, UserAccount, ContractAccount<Ve
                 (OCTOBER_1_2021 - ONE_MONTH).into(),
                 TWO_YEARS.into(), // duration
                 U64::from(0), // cliff
-                true// revocable
+                true, // revocable
+                None // transfer_policy
                 )
         );
     } else {
@@ -106,7 +117,8 @@ pub fn init(is_one_month: bool) -> (UserAccount, UserAccount, ContractAccount<Ve
                 JUNE_1_2021.into(), // start
                 TWO_YEARS.into(), // duration
                 SIX_MONTHS.into(), // cliff
-                true// revocable
+                true, // revocable
+                None // transfer_policy
                 )
         );
     }
@@ -114,4 +126,114 @@ pub fn init(is_one_month: bool) -> (UserAccount, UserAccount, ContractAccount<Ve
     register_user(&vesting.user_account);
 
     (root, ft, vesting, alice)
-}
\ No newline at end of file
+}
+
+// native NEAR grant, the account keeps storage_amount for its own storage
+pub fn init_native(root: &UserAccount, recipient: &UserAccount, storage_amount: u128, amount: u128) -> UserAccount {
+    let vesting_native = root.deploy(
+        &VESTING_WASM_BYTES,
+        "vesting-native".to_string(),
+        storage_amount,
+    );
+    root.call(
+        vesting_native.account_id(),
+        "new_native",
+        &json!({
+            "owner": root.valid_account_id(),
+            "recipient": recipient.valid_account_id(),
+            "start": U64::from(JUNE_1_2021),
+            "duration": U64::from(TWO_YEARS),
+            "cliff_duration": U64::from(SIX_MONTHS),
+            "revocable": true,
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS,
+        amount, // vested amount
+    )
+    .assert_success();
+
+    vesting_native
+}
+
+pub fn init_staking_pool(root: &UserAccount) -> UserAccount {
+    let staking_pool = root.deploy(
+        &MOCK_STAKING_POOL_WASM_BYTES,
+        STAKING_POOL_ID.to_string(),
+        STORAGE_AMOUNT,
+    );
+    root.call(
+        staking_pool.account_id(),
+        "new",
+        &json!({}).to_string().into_bytes(),
+        DEFAULT_GAS,
+        0,
+    )
+    .assert_success();
+
+    staking_pool
+}
+
+// Sputnik DAO v2 where every council member can vote, a single vote passes a proposal
+pub fn init_dao(root: &UserAccount, council: &UserAccount) -> UserAccount {
+    let dao_wasm_bytes = std::fs::read(SPUTNIKDAO2_WASM_PATH)
+        .unwrap_or_else(|_| panic!(
+            "{} is missing, copy sputnikdao2/res/sputnikdao2.wasm from near-daos/sputnik-dao-contract (see Testing in the README)",
+            SPUTNIKDAO2_WASM_PATH
+        ));
+    let dao = root.deploy(
+        &dao_wasm_bytes,
+        DAO_ID.to_string(),
+        to_yocto("20"),
+    );
+    root.call(
+        dao.account_id(),
+        "new",
+        &json!({
+            "config": {
+                "name": "paras-dao",
+                "purpose": "Paras grants",
+                "metadata": "",
+            },
+            "policy": [council.account_id()],
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS,
+        0,
+    )
+    .assert_success();
+
+    dao
+}
+
+// the same grant as init(false), owned by the DAO
+pub fn init_dao_grant(root: &UserAccount, dao: &UserAccount, recipient: &UserAccount) -> UserAccount {
+    let vesting = root.deploy(
+        &VESTING_WASM_BYTES,
+        "vesting-dao".to_string(),
+        STORAGE_AMOUNT,
+    );
+    root.call(
+        vesting.account_id(),
+        "new",
+        &json!({
+            "owner": dao.valid_account_id(),
+            "recipient": recipient.valid_account_id(),
+            "token": FT_ID,
+            "amount": U128::from(ONE_MILLION_COIN),
+            "start": U64::from(JUNE_1_2021),
+            "duration": U64::from(TWO_YEARS),
+            "cliff_duration": U64::from(SIX_MONTHS),
+            "revocable": true,
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS,
+        0,
+    )
+    .assert_success();
+    register_user(&vesting);
+
+    vesting
+}